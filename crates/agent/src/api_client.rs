@@ -1,65 +1,215 @@
+use core::auth::signature::{sign, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use core::dto::error_body::{ErrorBody, UNKNOWN_AGENT};
+use core::dto::deregister_payload::DeregisterPayload;
 use core::dto::register_payload::RegisterPayload;
+use core::dto::register_response::RegisterResponse;
 use core::dto::heart_beat::Heartbeat;
+use core::dto::network_interface::NetworkInterface;
 use core::dto::update_id::IpUpdatePayload;
 use core::logging::{debug, info};
 use anyhow::Result;
+use serde::Serialize;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const API_PREFIX: &str = "api/v1";
+// Used until the server hands out an interval, and with servers that predate negotiating it.
+const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Non-success response returned by the PiWatch server.
+#[derive(Debug)]
+pub(crate) struct ServerError {
+    pub status: reqwest::StatusCode,
+    pub body: Option<ErrorBody>,
+}
+
+impl std::fmt::Display for ServerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.body {
+            Some(body) => write!(f, "HTTP {} {}: {}", self.status, body.code, body.message),
+            None => write!(f, "HTTP {}", self.status),
+        }
+    }
+}
+
+impl std::error::Error for ServerError {}
+
+impl ServerError {
+    /// Whether the server lost track of this agent and expects it to register again.
+    pub(crate) fn is_unknown_agent(&self) -> bool {
+        self.body.as_ref().is_some_and(|body| body.code == UNKNOWN_AGENT)
+    }
+}
+
+struct Endpoint {
+    server_url: String,
+    agent_secret: String,
+    aliases: Vec<String>,
+    heartbeat_interval_secs: Option<u64>,
+}
 
 #[derive(Clone)]
 pub(crate) struct ApiClient {
    client: reqwest::Client,
-   server_url: String,
+   endpoint: Arc<RwLock<Endpoint>>,
    hostname: String,
+   agent_id: String,
+   last_contact: Arc<Mutex<Option<SystemTime>>>,
+   heartbeat_interval: Arc<Mutex<Duration>>,
 }
 
 impl ApiClient {
-    pub(crate) fn new(client: reqwest::Client, piwatch_server_url: &str) -> Result<Self> {
+    pub(crate) fn new(
+        client: reqwest::Client,
+        piwatch_server_url: &str,
+        agent_secret: &str,
+        aliases: &[String],
+        heartbeat_interval_secs: Option<u64>,
+        agent_id: String,
+    ) -> Result<Self> {
         Ok(Self {
-            client: client,
-            server_url: piwatch_server_url.to_string(),
+            client,
+            endpoint: Arc::new(RwLock::new(Endpoint {
+                server_url: piwatch_server_url.to_string(),
+                agent_secret: agent_secret.to_string(),
+                aliases: aliases.to_vec(),
+                heartbeat_interval_secs,
+            })),
             hostname: hostname::get()?.to_string_lossy().to_string(),
+            agent_id,
+            last_contact: Arc::new(Mutex::new(None)),
+            heartbeat_interval: Arc::new(Mutex::new(DEFAULT_HEARTBEAT_INTERVAL)),
         })
     }
 
-    pub(crate) async fn register_agent(&self, ipv4: Option<String>) -> Result<()> {
+    /// Points every clone of this client at a new server, secret, set of aliases or requested heartbeat interval.
+    pub(crate) fn reconfigure(&self, piwatch_server_url: &str, agent_secret: &str, aliases: &[String], heartbeat_interval_secs: Option<u64>) {
+        let mut endpoint = self.endpoint.write().unwrap();
+        endpoint.server_url = piwatch_server_url.to_string();
+        endpoint.agent_secret = agent_secret.to_string();
+        endpoint.aliases = aliases.to_vec();
+        endpoint.heartbeat_interval_secs = heartbeat_interval_secs;
+    }
+
+    pub(crate) fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    pub(crate) fn agent_id(&self) -> &str {
+        &self.agent_id
+    }
+
+    /// When the server last answered a request with a success status.
+    pub(crate) fn last_contact(&self) -> Option<SystemTime> {
+        *self.last_contact.lock().unwrap()
+    }
+
+    /// Interval between heartbeats, as last agreed with the server.
+    pub(crate) fn heartbeat_interval(&self) -> Duration {
+        *self.heartbeat_interval.lock().unwrap()
+    }
 
-        let _ = self.client
-            .post(format!("{}/register", self.server_url))
-            .json(&RegisterPayload {
+    pub(crate) async fn register_agent(&self, ipv4: Option<String>, ipv6: Vec<String>, interfaces: Vec<NetworkInterface>) -> Result<()> {
+        let (aliases, heartbeat_interval_secs) = {
+            let endpoint = self.endpoint.read().unwrap();
+            (endpoint.aliases.clone(), endpoint.heartbeat_interval_secs)
+        };
+        let response = self.post_signed("register", &RegisterPayload {
+                agent_id: Some(self.agent_id.to_string()),
                 hostname: self.hostname.to_string(),
                 agent_version: env!("CARGO_PKG_VERSION").to_string(),
                 ipv4,
+                ipv6,
+                interfaces,
+                aliases,
+                heartbeat_interval_secs,
             })
-            .send()
             .await?;
 
+        // Older servers answer with an empty body, keep the current interval then
+        if let Ok(accepted) = response.json::<RegisterResponse>().await {
+            let interval = Duration::from_secs(accepted.heartbeat_interval_secs.max(1));
+            let mut current = self.heartbeat_interval.lock().unwrap();
+            if *current != interval {
+                info!("Heartbeat interval set to {}s by the server", interval.as_secs());
+                *current = interval;
+            }
+        }
+
         Ok(())
     }
 
     pub(crate) async fn send_heartbeat(&self) -> Result<()> {
-        let _ = self.client
-            .post(format!("{}/heartbeat", self.server_url))
-            .json(&Heartbeat {
+        self.post_signed("heartbeat", &Heartbeat {
+                agent_id: Some(self.agent_id.to_string()),
                 hostname: self.hostname.to_string(),
             })
-            .send()
             .await?;
 
         Ok(())
     }
 
-    pub(crate) async fn update_ip(&self, ipv4: Option<String>, event: String) -> Result<()> {
-        debug!("Sending IP update to server: event={} ip={}", event, ipv4.as_deref().unwrap_or("None"));
-        let _ = self.client
-            .post(format!("{}/update", self.server_url))
-            .json(&IpUpdatePayload {
+    /// Tells the server this agent is stopping on purpose, optionally keeping its DNS records.
+    pub(crate) async fn deregister(&self, keep_dns: bool) -> Result<()> {
+        self.post_signed("deregister", &DeregisterPayload {
+                agent_id: Some(self.agent_id.to_string()),
+                hostname: self.hostname.to_string(),
+                keep_dns,
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn update_ip(&self, ip: IpAddr, event: String, interface: Option<String>) -> Result<()> {
+        debug!("Sending IP update to server: event={} ip={}", event, ip);
+        let (ipv4, ipv6) = match ip {
+            IpAddr::V4(v4) => (Some(v4.to_string()), None),
+            IpAddr::V6(v6) => (None, Some(v6.to_string())),
+        };
+
+        self.post_signed("update", &IpUpdatePayload {
+                agent_id: Some(self.agent_id.to_string()),
                 hostname: self.hostname.to_string(),
                 ipv4,
+                ipv6,
                 event,
+                interface,
             })
-            .send()
             .await?;
         info!("IP update sent successfully");
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    async fn post_signed<T: Serialize>(&self, path: &str, payload: &T) -> Result<reqwest::Response> {
+        let body = serde_json::to_vec(payload)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let (url, signature) = {
+            let endpoint = self.endpoint.read().unwrap();
+            (
+                format!("{}/{}/{}", endpoint.server_url, API_PREFIX, path),
+                sign(&endpoint.agent_secret, "POST", &format!("/{}", path), timestamp, &body),
+            )
+        };
+
+        let response = self.client
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, signature)
+            .body(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.json::<ErrorBody>().await.ok();
+            return Err(ServerError { status, body }.into());
+        }
+
+        *self.last_contact.lock().unwrap() = Some(SystemTime::now());
+        Ok(response)
+    }
+}
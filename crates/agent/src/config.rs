@@ -1,10 +1,12 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::path::Path;
 use core::config::log::{logging, logging::LevelFilter};
 
 const CONFIG_PATH: &str = "config.json";
 const DEFAULT_BIND_PORT: u16 = 8887;
-const DEFAULT_LISTENING_INTERFACE: &'static str = "eth0";
+const DEFAULT_LISTENING_INTERFACE: &str = "eth0";
+const DEFAULT_QUEUE_CAPACITY: usize = 256;
+const DEFAULT_AGENT_ID_PATH: &str = "agent_id";
 
 pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
     match load_config_from_env() {
@@ -15,7 +17,7 @@ pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
                 create_default_config_file()?;
                 Err(Box::new(std::io::Error::new(
                     std::io::ErrorKind::NotFound,
-                    format!("You need to set a valid PIWATCH_SERVER_URL in environment variables or edit the created {} file", CONFIG_PATH),
+                    format!("You need to set a valid PIWATCH_SERVER_URL and AGENT_SECRET in environment variables or edit the created {} file", CONFIG_PATH),
                 )))
             }
         },
@@ -30,8 +32,24 @@ fn load_config_from_env() -> Result<Config, std::io::Error> {
         )
     })?;
 
-    let listening_interface = std::env::var("LISTENING_INTERFACE")
-        .unwrap_or(DEFAULT_LISTENING_INTERFACE.to_string());
+    let agent_secret = std::env::var("AGENT_SECRET").map_err(|_| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "AGENT_SECRET env var is undefined",
+        )
+    })?;
+
+    let listening_interfaces = std::env::var("LISTENING_INTERFACES")
+        .or_else(|_| std::env::var("LISTENING_INTERFACE"))
+        .map(|value| split_list(&value))
+        .unwrap_or_else(|_| vec![DEFAULT_LISTENING_INTERFACE.to_string()]);
+
+    if listening_interfaces.is_empty() {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "LISTENING_INTERFACES must name at least one interface",
+        ));
+    }
 
     let bind_port = std::env::var("BIND_PORT")
         .unwrap_or(DEFAULT_BIND_PORT.to_string())
@@ -53,11 +71,72 @@ fn load_config_from_env() -> Result<Config, std::io::Error> {
             )
         })?;
 
+    let queue_capacity = std::env::var("QUEUE_CAPACITY")
+        .unwrap_or(DEFAULT_QUEUE_CAPACITY.to_string())
+        .parse::<usize>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "QUEUE_CAPACITY must be a valid positive number",
+            )
+        })?;
+
+    let queue_path = std::env::var("QUEUE_PATH").ok();
+
+    let metrics_enabled = std::env::var("METRICS_ENABLED")
+        .unwrap_or("false".to_string())
+        .parse::<bool>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "METRICS_ENABLED must be true or false",
+            )
+        })?;
+
+    let api_token = std::env::var("AGENT_API_TOKEN").ok();
+
+    let agent_id_path = std::env::var("AGENT_ID_PATH")
+        .unwrap_or(DEFAULT_AGENT_ID_PATH.to_string());
+
+    let aliases = std::env::var("AGENT_ALIASES")
+        .map(|value| split_list(&value))
+        .unwrap_or_default();
+
+    let heartbeat_interval_secs = std::env::var("HEARTBEAT_INTERVAL_SECS")
+        .ok()
+        .map(|value| value.parse::<u64>())
+        .transpose()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "HEARTBEAT_INTERVAL_SECS must be a valid number of seconds",
+            )
+        })?;
+
+    let keep_dns_on_shutdown = std::env::var("KEEP_DNS_ON_SHUTDOWN")
+        .unwrap_or("false".to_string())
+        .parse::<bool>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "KEEP_DNS_ON_SHUTDOWN must be true or false",
+            )
+        })?;
+
     Ok(Config {
         piwatch_server_url,
-        listening_interface,
+        agent_secret,
+        listening_interfaces,
         bind_port,
-        log_level
+        log_level,
+        queue_capacity,
+        queue_path,
+        metrics_enabled,
+        api_token,
+        aliases,
+        agent_id_path,
+        heartbeat_interval_secs,
+        keep_dns_on_shutdown,
     })
 }
 
@@ -73,6 +152,13 @@ fn load_config_from_json() -> Result<Config, Box<dyn std::error::Error>> {
                 format!("Set a valid PIWATCH_SERVER_URL in {}", CONFIG_PATH),
             )));
         }
+
+        if config.agent_secret.is_empty() || config.agent_secret == default_config.agent_secret {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Set a valid AGENT_SECRET in {}", CONFIG_PATH),
+            )));
+        }
         return Ok(config);
     }
     
@@ -91,22 +177,88 @@ fn create_default_config_file() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 pub(crate) struct Config {
     pub piwatch_server_url: String,
-    pub listening_interface: String,
+    #[serde(default)]
+    pub agent_secret: String,
+    /// Interface names or glob patterns (`*`, `?`), in priority order.
+    #[serde(alias = "listening_interface", deserialize_with = "one_or_many")]
+    pub listening_interfaces: Vec<String>,
     pub bind_port: u16,
     #[serde(with = "logging")]
-    pub log_level: LevelFilter
+    pub log_level: LevelFilter,
+    #[serde(default = "default_queue_capacity")]
+    pub queue_capacity: usize,
+    #[serde(default)]
+    pub queue_path: Option<String>,
+    #[serde(default)]
+    pub metrics_enabled: bool,
+    /// Bearer token for the local control endpoints, which stay disabled without one.
+    #[serde(default)]
+    pub api_token: Option<String>,
+    /// Extra DNS names for this host, published by the server as CNAMEs, e.g. `grafana`.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// File holding the agent ID generated on first run.
+    #[serde(default = "default_agent_id_path")]
+    pub agent_id_path: String,
+    /// Heartbeat interval to ask the server for, it has the final say. The server default when unset.
+    #[serde(default)]
+    pub heartbeat_interval_secs: Option<u64>,
+    /// Leave this host's DNS records in place when the agent is stopped, instead of removing them.
+    #[serde(default)]
+    pub keep_dns_on_shutdown: bool,
+}
+
+// Older config files hold a single interface name.
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => split_list(&value),
+        OneOrMany::Many(values) => values,
+    })
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn default_queue_capacity() -> usize {
+    DEFAULT_QUEUE_CAPACITY
+}
+
+fn default_agent_id_path() -> String {
+    DEFAULT_AGENT_ID_PATH.to_string()
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
             piwatch_server_url: "piwatch_server_url".to_string(),
-            listening_interface: DEFAULT_LISTENING_INTERFACE.to_string(),
+            agent_secret: "agent_secret".to_string(),
+            listening_interfaces: vec![DEFAULT_LISTENING_INTERFACE.to_string()],
             bind_port: DEFAULT_BIND_PORT,
-            log_level: LevelFilter::INFO
+            log_level: LevelFilter::INFO,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            queue_path: None,
+            metrics_enabled: false,
+            api_token: None,
+            aliases: Vec::new(),
+            agent_id_path: DEFAULT_AGENT_ID_PATH.to_string(),
+            heartbeat_interval_secs: None,
+            keep_dns_on_shutdown: false,
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+use anyhow::{Context, Result};
+use core::logging::info;
+use std::path::Path;
+use uuid::Uuid;
+
+/// Reads the agent ID persisted at `path`, generating and saving a new one on first run.
+///
+/// The ID keeps the agent's server-side record stable across hostname changes and tells
+/// apart Pis that share a hostname.
+pub(crate) fn load_or_create(path: &str) -> Result<String> {
+    if Path::new(path).exists() {
+        let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read agent ID from {}", path))?;
+        let id = Uuid::parse_str(content.trim()).with_context(|| format!("Invalid agent ID in {}", path))?;
+        return Ok(id.to_string());
+    }
+
+    let id = Uuid::new_v4().to_string();
+    std::fs::write(path, format!("{}\n", id)).with_context(|| format!("Failed to save agent ID to {}", path))?;
+    info!("Generated agent ID {}", id);
+
+    Ok(id)
+}
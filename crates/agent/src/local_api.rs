@@ -0,0 +1,204 @@
+use crate::api_client::ApiClient;
+use crate::config::{load_config, Config};
+use crate::metrics::AgentMetrics;
+use crate::network::InterfaceAddresses;
+use crate::queue::ReportQueue;
+use axum::{
+    extract::{Request, State},
+    http::{header::{AUTHORIZATION, CONTENT_TYPE}, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use core::auth::bearer;
+use core::dto::error_body::ErrorBody;
+use core::dto::network_interface::NetworkInterface;
+use core::logging::{error, info, warn, LevelHandle};
+use serde::Serialize;
+use std::sync::{Arc, RwLock};
+use std::time::UNIX_EPOCH;
+
+#[derive(Clone)]
+pub(crate) struct LocalApiState {
+    pub api: ApiClient,
+    pub queue: ReportQueue,
+    pub addresses: InterfaceAddresses,
+    pub config: Arc<RwLock<Config>>,
+    pub log_level: Arc<LevelHandle>,
+    pub metrics: Arc<AgentMetrics>,
+}
+
+#[derive(Serialize)]
+struct AgentStatus {
+    agent_id: String,
+    hostname: String,
+    agent_version: String,
+    interfaces: Vec<NetworkInterface>,
+    /// Unix time of the last request the server accepted.
+    last_server_contact: Option<u64>,
+    queue_depth: usize,
+    heartbeat_interval_secs: u64,
+}
+
+#[derive(Serialize)]
+struct ReloadResult {
+    /// Settings that changed on disk but only take effect after a restart.
+    restart_required: Vec<&'static str>,
+}
+
+/// Serves the agent's local status and control API on `bind_port`.
+pub(crate) async fn serve(bind_port: u16, state: LocalApiState) -> anyhow::Result<()> {
+    let control_routes = Router::new()
+        .route("/control/register", post(force_register))
+        .route("/control/resync", post(force_resync))
+        .route("/control/reload", post(reload_config))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_token));
+
+    let mut app = Router::new()
+        .route("/status", get(status))
+        .merge(control_routes);
+
+    if state.config.read().unwrap().metrics_enabled {
+        app = app.route("/metrics", get(prometheus_metrics));
+    }
+
+    let listener = tokio::net::TcpListener::bind(("0.0.0.0", bind_port)).await?;
+    info!("Agent API listening on http://localhost:{}", bind_port);
+
+    axum::serve(listener, app.with_state(state)).await?;
+
+    Ok(())
+}
+
+async fn require_token(State(state): State<LocalApiState>, request: Request, next: Next) -> Response {
+    let api_token = state.config.read().unwrap().api_token.clone();
+    let Some(api_token) = api_token else {
+        return unauthorized("Control API is disabled, set AGENT_API_TOKEN to enable it");
+    };
+
+    let authorization = request.headers().get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+    if !bearer::matches(authorization, &api_token) {
+        warn!("Rejected {} {}: invalid API token", request.method(), request.uri());
+        return unauthorized("Invalid API token");
+    }
+
+    next.run(request).await
+}
+
+fn unauthorized(message: &str) -> Response {
+    let body = ErrorBody {
+        code: "unauthorized".to_string(),
+        message: message.to_string(),
+        retryable: false,
+    };
+
+    (StatusCode::UNAUTHORIZED, Json(body)).into_response()
+}
+
+async fn status(State(state): State<LocalApiState>) -> Json<AgentStatus> {
+    Json(AgentStatus {
+        agent_id: state.api.agent_id().to_string(),
+        hostname: state.api.hostname().to_string(),
+        agent_version: env!("CARGO_PKG_VERSION").to_string(),
+        interfaces: state.addresses.snapshot().await,
+        last_server_contact: state
+            .api
+            .last_contact()
+            .and_then(|at| at.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_secs()),
+        queue_depth: state.queue.len(),
+        heartbeat_interval_secs: state.api.heartbeat_interval().as_secs(),
+    })
+}
+
+async fn force_register(State(state): State<LocalApiState>) -> StatusCode {
+    info!("Re-registration requested through the local API");
+    state.queue.push(state.addresses.registration().await);
+
+    StatusCode::ACCEPTED
+}
+
+// A registration carries every current address, so queued updates only replay stale history.
+async fn force_resync(State(state): State<LocalApiState>) -> StatusCode {
+    info!("Full address resync requested through the local API");
+    state.queue.clear();
+    state.queue.push(state.addresses.registration().await);
+
+    StatusCode::ACCEPTED
+}
+
+async fn reload_config(State(state): State<LocalApiState>) -> Response {
+    let reloaded = match load_config() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("Failed to reload configuration: {}", e);
+            let body = ErrorBody {
+                code: "invalid_config".to_string(),
+                message: e.to_string(),
+                retryable: false,
+            };
+            return (StatusCode::UNPROCESSABLE_ENTITY, Json(body)).into_response();
+        }
+    };
+
+    if let Err(e) = state.log_level.set(&reloaded.log_level) {
+        error!("Failed to apply log level {}: {}", reloaded.log_level, e);
+    }
+    state.api.reconfigure(
+        &reloaded.piwatch_server_url,
+        &reloaded.agent_secret,
+        &reloaded.aliases,
+        reloaded.heartbeat_interval_secs,
+    );
+
+    let (restart_required, registration_changed) = {
+        let mut config = state.config.write().unwrap();
+        let mut restart_required = Vec::new();
+        if reloaded.listening_interfaces != config.listening_interfaces {
+            restart_required.push("listening_interfaces");
+        }
+        if reloaded.bind_port != config.bind_port {
+            restart_required.push("bind_port");
+        }
+        if reloaded.queue_capacity != config.queue_capacity || reloaded.queue_path != config.queue_path {
+            restart_required.push("queue");
+        }
+        if reloaded.agent_id_path != config.agent_id_path {
+            restart_required.push("agent_id_path");
+        }
+        if reloaded.metrics_enabled != config.metrics_enabled {
+            restart_required.push("metrics_enabled");
+        }
+
+        let registration_changed = config.aliases != reloaded.aliases
+            || config.heartbeat_interval_secs != reloaded.heartbeat_interval_secs;
+        config.piwatch_server_url = reloaded.piwatch_server_url;
+        config.agent_secret = reloaded.agent_secret;
+        config.log_level = reloaded.log_level;
+        config.api_token = reloaded.api_token;
+        config.aliases = reloaded.aliases;
+        config.heartbeat_interval_secs = reloaded.heartbeat_interval_secs;
+        config.keep_dns_on_shutdown = reloaded.keep_dns_on_shutdown;
+
+        (restart_required, registration_changed)
+    };
+
+    // The server only learns about aliases and the wanted heartbeat interval through a registration
+    if registration_changed {
+        state.queue.push(state.addresses.registration().await);
+    }
+
+    info!("Configuration reloaded");
+    Json(ReloadResult { restart_required }).into_response()
+}
+
+async fn prometheus_metrics(State(state): State<LocalApiState>) -> impl IntoResponse {
+    match state.metrics.render() {
+        Ok(body) => (StatusCode::OK, [(CONTENT_TYPE, prometheus::TEXT_FORMAT)], body),
+        Err(e) => {
+            error!("Failed to render metrics: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, [(CONTENT_TYPE, "text/plain")], String::new())
+        }
+    }
+}
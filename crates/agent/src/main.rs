@@ -1,14 +1,27 @@
 mod network;
 mod api_client;
 mod config;
+mod queue;
+mod metrics;
+mod local_api;
+mod identity;
 
-use std::{time::Duration};
 use tokio::time::sleep;
 use crate::config::load_config;
-use crate::{api_client::ApiClient};
+use crate::{api_client::{ApiClient, ServerError}};
 use crate::network::IpChangeListener;
+use crate::queue::ReportQueue;
+use crate::metrics::AgentMetrics;
+use crate::local_api::LocalApiState;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use core::logging::{error, info, warn};
 use anyhow::Result;
 
+// How long a stopping agent waits for the server to acknowledge the shutdown.
+const DEREGISTER_TIMEOUT: Duration = Duration::from_secs(5);
+
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     let config = match load_config() {
@@ -19,48 +32,96 @@ async fn main() -> Result<()> {
         }
     };
     
-    core::logging::init(&config.log_level);
+    let log_level = core::logging::init(&config.log_level);
 
+    let agent_id = identity::load_or_create(&config.agent_id_path)?;
     let client = reqwest::Client::new();
-    let api = ApiClient::new(client.clone(), &config.piwatch_server_url)?;
-    let ip_listener: IpChangeListener = match IpChangeListener::init(api.clone(), &config.listening_interface).await {
+    let api = ApiClient::new(client.clone(), &config.piwatch_server_url, &config.agent_secret, &config.aliases, config.heartbeat_interval_secs, agent_id)?;
+    let metrics = Arc::new(AgentMetrics::new()?);
+    let queue = ReportQueue::new(config.queue_capacity, config.queue_path.as_deref(), metrics.clone());
+    let delivery = queue.start(api.clone());
+
+    let ip_listener: IpChangeListener = match IpChangeListener::init(queue.clone(), &config.listening_interfaces).await {
         Ok(listener) => listener,
         Err(e) => {
             eprintln!("Failed to create IP change listener: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
     };
 
-    let ip: Option<String> = match ip_listener.get_initial_ipv4().await {
-        Some(ip) => Some(ip.to_string()),
-        None => None,
-    };
+    let addresses = ip_listener.addresses();
+    let shared_config = Arc::new(RwLock::new(config.clone()));
 
-    match api.register_agent(ip).await {
-        Ok(_) => println!("Successfully registered agent."),
-        Err(e) => {
-            eprintln!("Failed to register agent: {}", e);
-            return Err(e.into());
-        }
-    };
+    {
+        let bind_port = config.bind_port;
+        let state = LocalApiState {
+            api: api.clone(),
+            queue: queue.clone(),
+            addresses: addresses.clone(),
+            config: shared_config.clone(),
+            log_level: Arc::new(log_level),
+            metrics: metrics.clone(),
+        };
+        tokio::spawn(async move {
+            if let Err(e) = local_api::serve(bind_port, state).await {
+                error!("Agent API stopped: {}", e);
+            }
+        });
+    }
+
+    // Delivered in the background so a server or Pi-hole outage at boot doesn't stop the agent
+    queue.push(addresses.registration().await);
 
     // heartbeat
-    {
+    let heartbeat = {
         let api = api.clone();
+        let queue = queue.clone();
+        let metrics = metrics.clone();
         tokio::spawn(async move {
             loop {
                 match api.send_heartbeat().await {
-                    Ok(_) => (),
-                    Err(e) => eprintln!("Failed to send heartbeat: {}", e),
+                    Ok(_) => metrics.heartbeats.with_label_values(&["ok"]).inc(),
+                    Err(e) if e.downcast_ref::<ServerError>().is_some_and(ServerError::is_unknown_agent) => {
+                        metrics.heartbeats.with_label_values(&["unknown_agent"]).inc();
+                        warn!("Server no longer knows this agent, registering again");
+                        queue.push(addresses.registration().await);
+                    }
+                    Err(e) => {
+                        metrics.heartbeats.with_label_values(&["error"]).inc();
+                        eprintln!("Failed to send heartbeat: {}", e);
+                    }
                 }
 
-                sleep(Duration::from_secs(30)).await;
+                sleep(api.heartbeat_interval()).await;
             }
-        });
-    }
+        })
+    };
 
+    let mut terminate = signal(SignalKind::terminate())?;
+    let mut listener = ip_listener.start().await?;
     println!("Node started");
-    let _ = ip_listener.start().await?.await?;
+
+    tokio::select! {
+        result = &mut listener => {
+            heartbeat.abort();
+            let _ = result?;
+            return Ok(());
+        }
+        _ = terminate.recv() => info!("Received SIGTERM, shutting down"),
+        _ = tokio::signal::ctrl_c() => info!("Received SIGINT, shutting down"),
+    }
+
+    // Stop reporting before saying goodbye, so nothing revives the agent on the server afterwards
+    heartbeat.abort();
+    listener.abort();
+    delivery.abort();
+
+    let keep_dns = shared_config.read().unwrap().keep_dns_on_shutdown;
+    match tokio::time::timeout(DEREGISTER_TIMEOUT, api.deregister(keep_dns)).await {
+        Ok(Ok(_)) => info!("Deregistered from the server, keep_dns={}", keep_dns),
+        Ok(Err(e)) => warn!("Failed to deregister from the server: {}", e),
+        Err(_) => warn!("Server didn't acknowledge the shutdown within {:?}", DEREGISTER_TIMEOUT),
+    }
 
     Ok(())
 }
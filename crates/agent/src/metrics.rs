@@ -0,0 +1,49 @@
+use prometheus::{Encoder, IntCounterVec, IntGauge, Opts, Registry, TextEncoder};
+
+pub(crate) struct AgentMetrics {
+    registry: Registry,
+    pub reports_queued: IntCounterVec,
+    pub reports_sent: IntCounterVec,
+    pub heartbeats: IntCounterVec,
+    pub queue_depth: IntGauge,
+}
+
+impl AgentMetrics {
+    pub(crate) fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("piwatch_agent".to_string()), None)?;
+
+        let reports_queued = IntCounterVec::new(
+            Opts::new("reports_queued_total", "Reports queued for the server by type"),
+            &["type"],
+        )?;
+        let reports_sent = IntCounterVec::new(
+            Opts::new("reports_sent_total", "Report delivery attempts by type and outcome"),
+            &["type", "outcome"],
+        )?;
+        let heartbeats = IntCounterVec::new(
+            Opts::new("heartbeats_total", "Heartbeats sent by outcome"),
+            &["outcome"],
+        )?;
+        let queue_depth = IntGauge::new("queue_depth", "Reports waiting to be delivered")?;
+
+        registry.register(Box::new(reports_queued.clone()))?;
+        registry.register(Box::new(reports_sent.clone()))?;
+        registry.register(Box::new(heartbeats.clone()))?;
+        registry.register(Box::new(queue_depth.clone()))?;
+
+        Ok(Self {
+            registry,
+            reports_queued,
+            reports_sent,
+            heartbeats,
+            queue_depth,
+        })
+    }
+
+    pub(crate) fn render(&self) -> Result<String, prometheus::Error> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
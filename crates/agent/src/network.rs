@@ -1,54 +1,85 @@
-use crate::api_client::{ApiClient};
+use crate::queue::{Report, ReportQueue};
 use netlink_packet_route::{
     address::AddressAttribute,
     address::AddressMessage,
+    link::{LinkAttribute, LinkFlags, LinkMessage},
     RouteNetlinkMessage,
 };
 use netlink_packet_core::{NetlinkPayload,NetlinkMessage};
-use rtnetlink::{constants::RTMGRP_IPV4_IFADDR, new_connection};
+use rtnetlink::{constants::{RTMGRP_IPV4_IFADDR, RTMGRP_IPV6_IFADDR, RTMGRP_LINK}, new_connection};
 use futures::{StreamExt, TryStreamExt};
-use std::{net::{IpAddr, Ipv4Addr}};
+use core::dto::network_interface::NetworkInterface;
+use std::{net::{IpAddr, Ipv4Addr, Ipv6Addr}, sync::{Arc, RwLock}};
 use anyhow::{Result};
 use futures_channel::mpsc::UnboundedReceiver;
 use netlink_sys::{AsyncSocket, SocketAddr};
-use core::logging::{debug, info};
+use core::logging::{debug, info, warn};
+
+// IFA_F_TEMPORARY from linux/if_addr.h, set on RFC 4941 privacy addresses.
+const IFA_F_TEMPORARY: u32 = 0x01;
 
 pub(crate) struct IpChangeListener {
-    api: ApiClient,
-    link_index: u32,
+    queue: ReportQueue,
+    patterns: Vec<String>,
+    links: Links,
     messages: UnboundedReceiver<(NetlinkMessage<RouteNetlinkMessage>, SocketAddr)>,
-    handle: rtnetlink::Handle
+    addresses: InterfaceAddresses,
+}
+
+/// Watched links in priority order, updated as interfaces appear, get renamed or disappear.
+type Links = Arc<RwLock<Vec<WatchedLink>>>;
+
+#[derive(Clone, PartialEq)]
+struct WatchedLink {
+    priority: usize,
+    index: u32,
+    name: String,
+    up: bool,
+}
+
+/// Cloneable lookup of the current addresses on the watched interfaces.
+#[derive(Clone)]
+pub(crate) struct InterfaceAddresses {
+    handle: rtnetlink::Handle,
+    links: Links,
 }
 
 impl IpChangeListener {
-    pub(crate) async fn init(api: ApiClient, interface: &str) -> Result<Self> {
+    /// Watches every interface matching one of `patterns`, ranked by the first pattern it matches.
+    ///
+    /// Interfaces that don't exist yet are picked up when they appear.
+    pub(crate) async fn init(queue: ReportQueue, patterns: &[String]) -> Result<Self> {
         let (mut connection, handle, messages) = new_connection()?;
         
         connection.socket_mut().socket_mut().bind(
-            &SocketAddr::new(0, RTMGRP_IPV4_IFADDR as u32)
+            &SocketAddr::new(0, RTMGRP_LINK | RTMGRP_IPV4_IFADDR | RTMGRP_IPV6_IFADDR)
         )?;
         
         tokio::spawn(connection);
 
-        // resolve interface
-        let mut links = handle
-            .link()
-            .get()
-            .match_name(interface.to_string())
-            .execute();
+        // resolve interfaces
+        let mut links = handle.link().get().execute();
+        let mut watched = Vec::new();
+        while let Some(link) = links.try_next().await? {
+            if let Some(link) = watched_link(patterns, &link) {
+                watched.push(link);
+            }
+        }
+        watched.sort_by(|a, b| a.priority.cmp(&b.priority).then_with(|| a.name.cmp(&b.name)));
 
-        let link = links
-            .next()
-            .await
-            .ok_or_else(|| anyhow::anyhow!("interface not found"))??;
+        match watched.is_empty() {
+            true => warn!("No interface matches {} yet, waiting for one to appear", patterns.join(", ")),
+            false => info!("Watching interfaces: {}", link_names(&watched)),
+        }
 
-        let link_index = link.header.index;
+        let links: Links = Arc::new(RwLock::new(watched));
 
         Ok(Self {
-            api,
-            link_index,
+            queue,
+            patterns: patterns.to_vec(),
+            links: links.clone(),
             messages,
-            handle,
+            addresses: InterfaceAddresses { handle, links },
         })
     }
 
@@ -66,20 +97,8 @@ impl IpChangeListener {
         Ok(handle)
     }
 
-    pub(crate) async fn get_initial_ipv4(&self) -> Option<Ipv4Addr> {
-        let mut addrs = self.handle
-            .address()
-            .get()
-            .set_link_index_filter(self.link_index)
-            .execute();
-
-        while let Ok(Some(addr)) = addrs.try_next().await {
-            if let Some(ip) = extract_ipv4(&addr) {
-                return Some(ip);
-            }
-        }
-
-        None
+    pub(crate) fn addresses(&self) -> InterfaceAddresses {
+        self.addresses.clone()
     }
 
     async fn run(mut self) -> Result<()> {
@@ -92,38 +111,238 @@ impl IpChangeListener {
             let (addr, event) = match inner {
                 RouteNetlinkMessage::NewAddress(a) => (a, "add"),
                 RouteNetlinkMessage::DelAddress(a) => (a, "del"),
+                RouteNetlinkMessage::NewLink(link) => {
+                    self.link_changed(link.header.index, watched_link(&self.patterns, &link)).await;
+                    continue;
+                }
+                RouteNetlinkMessage::DelLink(link) => {
+                    self.link_changed(link.header.index, None).await;
+                    continue;
+                }
                 _ => continue,
             };
 
-            if addr.header.index != self.link_index {
+            let Some(interface) = self.link_name(addr.header.index) else {
                 continue;
-            }
+            };
 
-            let Some(ip) = extract_ipv4(&addr).map(|ip| ip.to_string()) else {
+            let Some(ip) = extract_ipv4(&addr)
+                .map(IpAddr::V4)
+                .or_else(|| extract_ipv6(&addr).map(IpAddr::V6)) else {
                 continue;
             };
 
             // TODO: specific endpoint for IP update/deletion
-            info!("Detected IP change: event={} ip={}", event, ip);
-            if let Err(e) = self.api.update_ip(
-                Some(ip),
-                event.to_string(),
-            ).await {
-                eprintln!("Failed to report IP change: {e}");
-            }
+            info!("Detected IP change: interface={} event={} ip={}", interface, event, ip);
+            self.queue.push(Report::Update {
+                ip,
+                event: event.to_string(),
+                interface: Some(interface),
+            });
         }
 
         Err(anyhow::anyhow!("IP changes subscription ended"))
     }
+
+    fn link_name(&self, index: u32) -> Option<String> {
+        let links = self.links.read().unwrap();
+        links.iter().find(|link| link.index == index).map(|link| link.name.clone())
+    }
+
+    /// Applies a link appearing, changing state, being renamed or removed, and sends the
+    /// server a fresh registration whenever the watched set or a link state changed.
+    async fn link_changed(&self, index: u32, link: Option<WatchedLink>) {
+        let changed = {
+            let mut links = self.links.write().unwrap();
+            let previous = links.iter().position(|watched| watched.index == index).map(|i| links.remove(i));
+            let changed = previous != link;
+
+            match (&previous, &link) {
+                (None, Some(added)) => info!("Interface {} appeared, watching it", added.name),
+                (Some(removed), None) => info!("Interface {} disappeared or was renamed, no longer watching it", removed.name),
+                (Some(before), Some(after)) if before.name != after.name => info!("Interface {} renamed to {}", before.name, after.name),
+                (Some(before), Some(after)) if before.up != after.up => {
+                    info!("Interface {} is {}", after.name, if after.up { "up" } else { "down" })
+                }
+                _ => {}
+            }
+
+            if let Some(link) = link {
+                let position = links
+                    .iter()
+                    .position(|watched| (watched.priority, &watched.name) > (link.priority, &link.name))
+                    .unwrap_or(links.len());
+                links.insert(position, link);
+            }
+
+            changed
+        };
+
+        if changed {
+            self.queue.push(self.addresses.registration().await);
+        }
+    }
+}
+
+impl InterfaceAddresses {
+    /// Current addresses and link state of each watched interface, in priority order.
+    pub(crate) async fn snapshot(&self) -> Vec<NetworkInterface> {
+        let links = self.links.read().unwrap().clone();
+        let mut interfaces = Vec::with_capacity(links.len());
+
+        for link in links {
+            let mut addrs = self.handle
+                .address()
+                .get()
+                .set_link_index_filter(link.index)
+                .execute();
+
+            let mut interface = NetworkInterface {
+                name: link.name,
+                up: link.up,
+                ipv4: None,
+                ipv6: Vec::new(),
+            };
+            while let Ok(Some(addr)) = addrs.try_next().await {
+                if let Some(ip) = extract_ipv4(&addr) {
+                    interface.ipv4.get_or_insert(ip.to_string());
+                } else if let Some(ip) = extract_ipv6(&addr) {
+                    interface.ipv6.push(ip.to_string());
+                }
+            }
+
+            interfaces.push(interface);
+        }
+
+        interfaces
+    }
+
+    /// Builds a full registration from the addresses currently on the watched interfaces.
+    pub(crate) async fn registration(&self) -> Report {
+        let interfaces = self.snapshot().await;
+        let up = || interfaces.iter().filter(|interface| interface.up);
+
+        Report::Register {
+            ipv4: up().find_map(|interface| interface.ipv4.clone()),
+            ipv6: up().flat_map(|interface| interface.ipv6.iter().cloned()).collect(),
+            interfaces,
+        }
+    }
+}
+
+/// The link to watch for `msg`, if its name matches one of `patterns`.
+fn watched_link(patterns: &[String], msg: &LinkMessage) -> Option<WatchedLink> {
+    let name = msg.attributes.iter().find_map(|attr| match attr {
+        LinkAttribute::IfName(name) => Some(name.clone()),
+        _ => None,
+    })?;
+    let priority = patterns.iter().position(|pattern| glob_match(pattern, &name))?;
+
+    // Administratively up with a carrier, an unplugged cable leaves the interface UP but not RUNNING.
+    let up = msg.header.flags.contains(LinkFlags::Up | LinkFlags::Running);
+
+    Some(WatchedLink {
+        priority,
+        index: msg.header.index,
+        name,
+        up,
+    })
+}
+
+fn link_names(links: &[WatchedLink]) -> String {
+    links.iter().map(|link| link.name.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+/// Matches an interface name against a pattern where `*` is any run of characters and `?` any single one.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) = (pattern.chars().collect(), name.chars().collect());
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                // let the last `*` swallow one more character
+                Some((star, matched)) => {
+                    backtrack = Some((star, matched + 1));
+                    p = star + 1;
+                    n = matched + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
 }
 
 fn extract_ipv4(msg: &AddressMessage) -> Option<Ipv4Addr> {
+    msg.attributes.iter().find_map(|attr| match attr {
+        AddressAttribute::Address(IpAddr::V4(v4)) => Some(*v4),
+        _ => None,
+    })
+}
+
+/// Returns the IPv6 address carried by `msg` if it should be published in DNS.
+///
+/// Link-local addresses are only reachable on-link and temporary privacy addresses
+/// rotate every few hours, so neither is worth an AAAA record.
+fn extract_ipv6(msg: &AddressMessage) -> Option<Ipv6Addr> {
+    let mut address = None;
+    let mut flags = msg.header.flags.bits() as u32;
+
     for attr in &msg.attributes {
-        if let AddressAttribute::Address(ip) = attr {
-            if let IpAddr::V4(v4) = ip {
-                return Some(*v4);
-            }
+        match attr {
+            AddressAttribute::Address(IpAddr::V6(v6)) => address = Some(*v6),
+            AddressAttribute::Flags(f) => flags = f.bits(),
+            _ => {}
         }
     }
-    None
-}
\ No newline at end of file
+
+    let ip = address?;
+    if ip.is_loopback() || ip.is_unicast_link_local() || flags & IFA_F_TEMPORARY != 0 {
+        debug!("Ignoring IPv6 address {} by policy", ip);
+        return None;
+    }
+
+    Some(ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_matches_literal_names() {
+        assert!(glob_match("eth0", "eth0"));
+        assert!(!glob_match("eth0", "eth1"));
+        assert!(!glob_match("eth0", "eth01"));
+        assert!(!glob_match("eth01", "eth0"));
+    }
+
+    #[test]
+    fn glob_matches_wildcards() {
+        assert!(glob_match("eth*", "eth0"));
+        assert!(glob_match("eth*", "eth"));
+        assert!(glob_match("wlan?", "wlan0"));
+        assert!(!glob_match("wlan?", "wlan"));
+        assert!(glob_match("*", "enp3s0"));
+        assert!(glob_match("en*s0", "enp3s0"));
+        assert!(!glob_match("en*s0", "enp3s1"));
+    }
+
+    #[test]
+    fn glob_backtracks_past_early_matches() {
+        assert!(glob_match("*s0*s0", "enp3s0s0"));
+        assert!(glob_match("e*0", "eth0veth0"));
+        assert!(!glob_match("e*1", "eth0veth0"));
+    }
+}
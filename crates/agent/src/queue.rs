@@ -0,0 +1,284 @@
+use crate::api_client::{ApiClient, ServerError};
+use crate::metrics::AgentMetrics;
+use core::dto::network_interface::NetworkInterface;
+use core::logging::{debug, error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::sync::Notify;
+
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Report {
+    Register {
+        ipv4: Option<String>,
+        ipv6: Vec<String>,
+        #[serde(default)]
+        interfaces: Vec<NetworkInterface>,
+    },
+    Update {
+        ip: IpAddr,
+        event: String,
+        #[serde(default)]
+        interface: Option<String>,
+    },
+}
+
+impl Report {
+    fn kind(&self) -> &'static str {
+        match self {
+            Report::Register { .. } => "register",
+            Report::Update { .. } => "update",
+        }
+    }
+
+    // Only the latest registration and the latest event per address are worth delivering.
+    fn supersedes(&self, other: &Report) -> bool {
+        match (self, other) {
+            (Report::Register { .. }, Report::Register { .. }) => true,
+            (Report::Update { ip: a, .. }, Report::Update { ip: b, .. }) => a == b,
+            _ => false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct Entry {
+    id: u64,
+    report: Report,
+}
+
+#[derive(Default)]
+struct Pending {
+    entries: VecDeque<Entry>,
+    next_id: u64,
+}
+
+/// Bounded outbound queue of reports for the server, retried with jittered exponential backoff.
+#[derive(Clone)]
+pub(crate) struct ReportQueue {
+    pending: Arc<Mutex<Pending>>,
+    notify: Arc<Notify>,
+    capacity: usize,
+    path: Option<PathBuf>,
+    metrics: Arc<AgentMetrics>,
+}
+
+impl ReportQueue {
+    pub(crate) fn new(capacity: usize, path: Option<&str>, metrics: Arc<AgentMetrics>) -> Self {
+        let path = path.map(PathBuf::from);
+        let pending = path.as_ref().map(load_pending).unwrap_or_default();
+
+        if !pending.entries.is_empty() {
+            info!("Restored {} pending report(s)", pending.entries.len());
+        }
+        metrics.queue_depth.set(pending.entries.len() as i64);
+
+        Self {
+            pending: Arc::new(Mutex::new(pending)),
+            notify: Arc::new(Notify::new()),
+            capacity: capacity.max(1),
+            path,
+            metrics,
+        }
+    }
+
+    pub(crate) fn push(&self, report: Report) {
+        self.metrics.reports_queued.with_label_values(&[report.kind()]).inc();
+
+        {
+            let mut pending = self.pending.lock().unwrap();
+            pending.entries.retain(|entry| !report.supersedes(&entry.report));
+
+            while pending.entries.len() >= self.capacity {
+                if let Some(dropped) = pending.entries.pop_front() {
+                    warn!("Report queue full, dropping {:?}", dropped.report);
+                }
+            }
+
+            let id = pending.next_id;
+            pending.next_id += 1;
+            pending.entries.push_back(Entry { id, report });
+            self.save(&pending);
+        }
+
+        self.notify.notify_one();
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.pending.lock().unwrap().entries.len()
+    }
+
+    /// Drops every pending report, used before a full resync makes them redundant.
+    pub(crate) fn clear(&self) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.entries.clear();
+        self.save(&pending);
+    }
+
+    pub(crate) fn start(&self, api: ApiClient) -> tokio::task::JoinHandle<()> {
+        let queue = self.clone();
+        tokio::spawn(async move { queue.run(api).await })
+    }
+
+    async fn run(self, api: ApiClient) {
+        let mut backoff = INITIAL_BACKOFF;
+
+        loop {
+            let Some((id, report)) = self.front() else {
+                self.notify.notified().await;
+                continue;
+            };
+
+            let result = send(&api, &report).await;
+            let outcome = match &result {
+                Ok(_) => "ok",
+                Err(e) if !is_retryable(e) => "dropped",
+                Err(_) => "retry",
+            };
+            self.metrics.reports_sent.with_label_values(&[report.kind(), outcome]).inc();
+
+            match result {
+                Ok(_) => {
+                    debug!("Delivered {:?}", report);
+                    self.remove(id);
+                    backoff = INITIAL_BACKOFF;
+                }
+                Err(e) if !is_retryable(&e) => {
+                    error!("Dropping {:?}, server rejected it: {}", report, e);
+                    self.remove(id);
+                    // The server answered, so it is reachable again
+                    backoff = INITIAL_BACKOFF;
+                }
+                Err(e) => {
+                    let delay = jittered(backoff);
+                    warn!("Failed to deliver {:?}, retrying in {:?}: {}", report, delay, e);
+                    tokio::time::sleep(delay).await;
+                    backoff = next_backoff(backoff);
+                }
+            }
+        }
+    }
+
+    fn front(&self) -> Option<(u64, Report)> {
+        let pending = self.pending.lock().unwrap();
+        pending.entries.front().map(|entry| (entry.id, entry.report.clone()))
+    }
+
+    // The entry may already be gone if a newer report superseded it while it was in flight.
+    fn remove(&self, id: u64) {
+        let mut pending = self.pending.lock().unwrap();
+        pending.entries.retain(|entry| entry.id != id);
+        self.save(&pending);
+    }
+
+    fn save(&self, pending: &Pending) {
+        self.metrics.queue_depth.set(pending.entries.len() as i64);
+
+        let Some(path) = &self.path else {
+            return;
+        };
+
+        if let Err(e) = write_atomically(path, &pending.entries) {
+            error!("Failed to save report queue to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Writes a sibling temp file and renames it over `path`, a crash mid-write leaves the previous queue intact.
+fn write_atomically(path: &Path, entries: &VecDeque<Entry>) -> anyhow::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let mut file = std::fs::File::create(&tmp_path)?;
+    serde_json::to_writer(&mut file, entries)?;
+    file.sync_all()?;
+    std::fs::rename(&tmp_path, path)?;
+
+    Ok(())
+}
+
+async fn send(api: &ApiClient, report: &Report) -> anyhow::Result<()> {
+    match report {
+        Report::Register { ipv4, ipv6, interfaces } => api.register_agent(ipv4.clone(), ipv6.clone(), interfaces.clone()).await,
+        Report::Update { ip, event, interface } => api.update_ip(*ip, event.clone(), interface.clone()).await,
+    }
+}
+
+// Spreads retries between half and one and a half times the backoff so agents don't retry in lockstep.
+fn jittered(backoff: Duration) -> Duration {
+    backoff.mul_f64(0.5 + fastrand::f64())
+}
+
+fn next_backoff(backoff: Duration) -> Duration {
+    (backoff * 2).min(MAX_BACKOFF)
+}
+
+// Transport failures and 5xx are worth retrying, rejected payloads will be rejected again.
+fn is_retryable(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<ServerError>() {
+        Some(server_error) => match &server_error.body {
+            Some(body) => body.retryable,
+            None => server_error.status.is_server_error(),
+        },
+        None => true,
+    }
+}
+
+fn load_pending(path: &PathBuf) -> Pending {
+    let entries: VecDeque<Entry> = match std::fs::File::open(path) {
+        Ok(file) => serde_json::from_reader(std::io::BufReader::new(file)).unwrap_or_else(|e| {
+            warn!("Ignoring unreadable report queue {}: {}", path.display(), e);
+            VecDeque::new()
+        }),
+        Err(_) => VecDeque::new(),
+    };
+
+    let next_id = entries.iter().map(|entry| entry.id + 1).max().unwrap_or(0);
+    Pending { entries, next_id }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::dto::error_body::ErrorBody;
+    use reqwest::StatusCode;
+
+    fn server_error(status: StatusCode, retryable: Option<bool>) -> anyhow::Error {
+        let body = retryable.map(|retryable| ErrorBody { code: "test".to_string(), message: "test".to_string(), retryable });
+        ServerError { status, body }.into()
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        assert_eq!(next_backoff(INITIAL_BACKOFF), Duration::from_secs(2));
+        assert_eq!(next_backoff(Duration::from_secs(200)), MAX_BACKOFF);
+        assert_eq!(next_backoff(MAX_BACKOFF), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn jitter_stays_around_the_backoff() {
+        for _ in 0..100 {
+            let delay = jittered(Duration::from_secs(10));
+            assert!(delay >= Duration::from_secs(5) && delay < Duration::from_secs(15), "{:?}", delay);
+        }
+    }
+
+    #[test]
+    fn retries_transport_and_server_failures_only() {
+        assert!(is_retryable(&anyhow::anyhow!("connection refused")));
+        assert!(is_retryable(&server_error(StatusCode::BAD_GATEWAY, None)));
+        assert!(!is_retryable(&server_error(StatusCode::BAD_REQUEST, None)));
+        // The server's verdict wins over the status code
+        assert!(is_retryable(&server_error(StatusCode::CONFLICT, Some(true))));
+        assert!(!is_retryable(&server_error(StatusCode::INTERNAL_SERVER_ERROR, Some(false))));
+    }
+}
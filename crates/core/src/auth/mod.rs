@@ -0,0 +1,2 @@
+pub mod bearer;
+pub mod signature;
@@ -0,0 +1,89 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+pub const SIGNATURE_HEADER: &str = "x-piwatch-signature";
+pub const TIMESTAMP_HEADER: &str = "x-piwatch-timestamp";
+
+/// Maximum clock drift accepted between agent and server, in seconds.
+pub const MAX_TIMESTAMP_SKEW_SECS: u64 = 300;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Signs `{method} {path}\n{timestamp}.{body}` with HMAC-SHA256 and returns the lowercase hex digest.
+///
+/// `path` is the endpoint below the API prefix, e.g. `/heartbeat`, so a body signed for one
+/// endpoint can't be replayed to another.
+pub fn sign(secret: &str, method: &str, path: &str, timestamp: u64, body: &[u8]) -> String {
+    let mac = mac_for(secret, method, path, timestamp, body);
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Checks `signature` against the request it claims to sign, in constant time.
+pub fn verify(secret: &str, method: &str, path: &str, timestamp: u64, body: &[u8], signature: &str) -> bool {
+    let Some(expected) = decode_hex(signature) else {
+        return false;
+    };
+
+    mac_for(secret, method, path, timestamp, body).verify_slice(&expected).is_ok()
+}
+
+fn mac_for(secret: &str, method: &str, path: &str, timestamp: u64, body: &[u8]) -> HmacSha256 {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(method.to_ascii_uppercase().as_bytes());
+    mac.update(b" ");
+    mac.update(path.as_bytes());
+    mac.update(b"\n");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    mac
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    if !value.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..value.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(value.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "s3cret";
+    const BODY: &[u8] = br#"{"agent_id":"a","hostname":"pi"}"#;
+
+    #[test]
+    fn accepts_its_own_signature() {
+        let signature = sign(SECRET, "POST", "/heartbeat", 1_700_000_000, BODY);
+
+        assert!(verify(SECRET, "POST", "/heartbeat", 1_700_000_000, BODY, &signature));
+        assert!(verify(SECRET, "post", "/heartbeat", 1_700_000_000, BODY, &signature.to_uppercase()));
+    }
+
+    #[test]
+    fn rejects_anything_signed_for_another_request() {
+        let signature = sign(SECRET, "POST", "/heartbeat", 1_700_000_000, BODY);
+
+        assert!(!verify(SECRET, "POST", "/deregister", 1_700_000_000, BODY, &signature));
+        assert!(!verify(SECRET, "GET", "/heartbeat", 1_700_000_000, BODY, &signature));
+        assert!(!verify(SECRET, "POST", "/heartbeat", 1_700_000_001, BODY, &signature));
+        assert!(!verify(SECRET, "POST", "/heartbeat", 1_700_000_000, b"{}", &signature));
+        assert!(!verify("other", "POST", "/heartbeat", 1_700_000_000, BODY, &signature));
+    }
+
+    #[test]
+    fn rejects_malformed_signatures() {
+        assert!(!verify(SECRET, "POST", "/heartbeat", 1_700_000_000, BODY, ""));
+        assert!(!verify(SECRET, "POST", "/heartbeat", 1_700_000_000, BODY, "abc"));
+        assert!(!verify(SECRET, "POST", "/heartbeat", 1_700_000_000, BODY, "zz"));
+    }
+}
@@ -1,5 +1,5 @@
 pub mod logging {
-    use tracing_subscriber::EnvFilter;
+    use tracing_subscriber::{fmt::Formatter, reload, EnvFilter};
     use serde::{Deserialize, Deserializer, Serializer};
 
     pub use tracing::{trace, error, info, warn, debug};
@@ -35,9 +35,22 @@ pub mod logging {
         }
     }
 
-    pub fn init(level: &LevelFilter) {
-        tracing_subscriber::fmt()
+    /// Changes the log level of the global subscriber after [`init`].
+    pub struct LevelHandle(reload::Handle<EnvFilter, Formatter>);
+
+    impl LevelHandle {
+        pub fn set(&self, level: &LevelFilter) -> Result<(), reload::Error> {
+            self.0.reload(EnvFilter::new(level.to_string()))
+        }
+    }
+
+    pub fn init(level: &LevelFilter) -> LevelHandle {
+        let builder = tracing_subscriber::fmt()
             .with_env_filter(EnvFilter::new(level.to_string()))
-            .init();
+            .with_filter_reloading();
+        let handle = builder.reload_handle();
+        builder.init();
+
+        LevelHandle(handle)
     }
 }
\ No newline at end of file
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Sent by an agent stopping on purpose, so the server doesn't treat it as a crash.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Deserialize, Serialize)]
+pub struct DeregisterPayload {
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    pub hostname: String,
+    /// Keep the agent's DNS records published while it is down, e.g. for a quick restart.
+    #[serde(default)]
+    pub keep_dns: bool,
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Error code returned when the server has no record of the calling agent.
+pub const UNKNOWN_AGENT: &str = "unknown_agent";
+pub const HOSTNAME_CONFLICT: &str = "hostname_conflict";
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ErrorBody {
+    pub code: String,
+    pub message: String,
+    pub retryable: bool,
+}
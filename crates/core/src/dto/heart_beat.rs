@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Deserialize, Serialize)]
 pub struct Heartbeat {
+    /// Persistent ID generated by the agent on first run, absent for agents that predate it.
+    #[serde(default)]
+    pub agent_id: Option<String>,
     pub hostname: String,
 }
\ No newline at end of file
@@ -1,3 +1,7 @@
 pub mod register_payload;
 pub mod update_id;
-pub mod heart_beat;
\ No newline at end of file
+pub mod heart_beat;
+pub mod error_body;
+pub mod network_interface;
+pub mod register_response;
+pub mod deregister_payload;
\ No newline at end of file
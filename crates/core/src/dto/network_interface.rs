@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// Addresses currently held by one watched interface of an agent.
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct NetworkInterface {
+    pub name: String,
+    /// Whether the link is up with a carrier, addresses of a down link are not published.
+    #[serde(default = "default_up")]
+    pub up: bool,
+    pub ipv4: Option<String>,
+    #[serde(default)]
+    pub ipv6: Vec<String>,
+}
+
+// Agents that predate link state reporting only ever report usable interfaces.
+fn default_up() -> bool {
+    true
+}
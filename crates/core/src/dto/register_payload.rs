@@ -1,8 +1,24 @@
+use crate::dto::network_interface::NetworkInterface;
 use serde::{Deserialize, Serialize};
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Deserialize, Serialize)]
 pub struct RegisterPayload {
+    /// Persistent ID generated by the agent on first run, absent for agents that predate it.
+    #[serde(default)]
+    pub agent_id: Option<String>,
     pub hostname: String,
     pub agent_version: String,
     pub ipv4: Option<String>,
-}
\ No newline at end of file
+    #[serde(default)]
+    pub ipv6: Vec<String>,
+    /// Watched interfaces in priority order, the first one holding an IPv4 address gets the A record.
+    #[serde(default)]
+    pub interfaces: Vec<NetworkInterface>,
+    /// Extra names for the agent, published as CNAMEs pointing at its hostname.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Heartbeat interval the agent would like, the server answers with the one to use.
+    #[serde(default)]
+    pub heartbeat_interval_secs: Option<u64>,
+}
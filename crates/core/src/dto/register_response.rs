@@ -0,0 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[derive(Deserialize, Serialize)]
+pub struct RegisterResponse {
+    /// Seconds between heartbeats, agents missing several in a row are reported offline.
+    pub heartbeat_interval_secs: u64,
+}
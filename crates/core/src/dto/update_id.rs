@@ -1,8 +1,16 @@
 use serde::{Deserialize, Serialize};
 
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[derive(Deserialize, Serialize)]
 pub struct IpUpdatePayload {
+    /// Persistent ID generated by the agent on first run, absent for agents that predate it.
+    #[serde(default)]
+    pub agent_id: Option<String>,
     pub hostname: String,
     pub ipv4: Option<String>,
+    #[serde(default)]
+    pub ipv6: Option<String>,
     pub event: String, // "add" | "del"
-}
\ No newline at end of file
+    #[serde(default)]
+    pub interface: Option<String>,
+}
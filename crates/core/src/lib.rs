@@ -1,4 +1,5 @@
 pub mod dto;
 pub mod config;
+pub mod auth;
 
-pub use config::log::logging;
\ No newline at end of file
+pub use config::log::logging;
@@ -0,0 +1,110 @@
+use crate::model::event::{Event, RecordedEvent};
+use crate::notify::notifier::Notifier;
+use crate::storage::{agent_store::StoreResult, event_store::{EventFilter, EventStore}};
+use core::logging::{error, info};
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+use std::time::{Duration, SystemTime};
+use tokio::sync::broadcast;
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(3600);
+/// Events a slow live subscriber may fall behind before it starts missing some.
+const LIVE_BUFFER: usize = 256;
+
+/// Keeps the history of every event, passes the notable ones on to the notifier
+/// and broadcasts all of them to live subscribers.
+pub(crate) struct EventLog {
+    store: Arc<dyn EventStore>,
+    notifier: Arc<Notifier>,
+    live: broadcast::Sender<RecordedEvent>,
+    dns_failing: AtomicBool,
+}
+
+impl EventLog {
+    pub(crate) fn new(store: Arc<dyn EventStore>, notifier: Arc<Notifier>) -> Self {
+        Self {
+            store,
+            notifier,
+            live: broadcast::channel(LIVE_BUFFER).0,
+            dns_failing: AtomicBool::new(false),
+        }
+    }
+
+    pub(crate) fn record(&self, event: Event) {
+        let notify = event.is_notable();
+        self.emit(event, notify);
+    }
+
+    /// Reports the outcome of publishing to the DNS backend.
+    ///
+    /// Every failure goes to the history, webhooks only hear when it starts or stops failing.
+    pub(crate) fn dns_sync(&self, backend: &str, result: Result<(), String>) {
+        match result {
+            Err(error) => {
+                let started_failing = !self.dns_failing.swap(true, Ordering::SeqCst);
+                self.emit(Event::DnsSyncFailed { backend: backend.to_string(), error }, started_failing);
+            }
+            Ok(_) if self.dns_failing.swap(false, Ordering::SeqCst) => {
+                self.record(Event::DnsSyncRecovered { backend: backend.to_string() });
+            }
+            Ok(_) => {}
+        }
+    }
+
+    pub(crate) fn query(&self, filter: &EventFilter) -> StoreResult<Vec<RecordedEvent>> {
+        self.store.load_events(filter)
+    }
+
+    /// Sends `event` to live subscribers only, without keeping it.
+    pub(crate) fn stream(&self, event: Event) {
+        self.broadcast(0, SystemTime::now(), event);
+    }
+
+    /// Events recorded or streamed from now on, as they happen.
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<RecordedEvent> {
+        self.live.subscribe()
+    }
+
+    fn emit(&self, event: Event, notify: bool) {
+        let at = SystemTime::now();
+        let seq = match self.store.append_event(at, &event) {
+            Ok(seq) => seq,
+            Err(e) => {
+                error!("Failed to record {} event: {}", event.kind(), e);
+                // still worth showing live, without a place in the history
+                0
+            }
+        };
+
+        match notify {
+            true => {
+                self.broadcast(seq, at, event.clone());
+                self.notifier.notify(event);
+            }
+            false => self.broadcast(seq, at, event),
+        }
+    }
+
+    fn broadcast(&self, seq: i64, at: SystemTime, event: Event) {
+        if self.live.receiver_count() > 0 {
+            let _ = self.live.send(RecordedEvent { seq, at: at.into(), event });
+        }
+    }
+}
+
+/// Drops events older than `retention` every hour.
+pub(crate) fn spawn_retention(store: Arc<dyn EventStore>, retention: Duration) {
+    tokio::spawn(async move {
+        loop {
+            match store.prune_events(SystemTime::now() - retention) {
+                Ok(0) => {}
+                Ok(pruned) => info!("Pruned {} event(s) older than {} day(s)", pruned, retention.as_secs() / 86400),
+                Err(e) => error!("Failed to prune the event history: {}", e),
+            }
+
+            tokio::time::sleep(PRUNE_INTERVAL).await;
+        }
+    });
+}
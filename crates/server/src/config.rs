@@ -1,9 +1,19 @@
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use crate::model::event::Event;
 use core::config::log::{logging, logging::LevelFilter};
 
-const CONFIG_PATH: &'static str = "config.json";
+const CONFIG_PATH: &str = "config.json";
 const DEFAULT_BIND_PORT: u16 = 8888;
+const DEFAULT_DATABASE_PATH: &str = "piwatch.db";
+const DEFAULT_RECONCILE_INTERVAL_SECS: u64 = 300;
+const DEFAULT_HOSTS_FILE_PATH: &str = "piwatch.hosts";
+const DEFAULT_HEARTBEAT_INTERVAL_SECS: u64 = 30;
+const DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_HEARTBEAT_MISS_FACTOR: u32 = 4;
+const DEFAULT_LIVENESS_CHECK_INTERVAL_SECS: u64 = 15;
+const DEFAULT_PURGE_AFTER_HOURS: u64 = 168;
+const DEFAULT_EVENT_RETENTION_DAYS: u64 = 30;
 
 pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
     match load_config_from_env() {
@@ -14,7 +24,7 @@ pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
                 create_default_config_file()?;
                 Err(Box::new(std::io::Error::new(
                     std::io::ErrorKind::NotFound,
-                    format!("You need to set a valid PIHOLE_URL and PIHOLE_PASS in environment variables or edit the created {} file.", CONFIG_PATH),
+                    format!("You need to set a valid PIHOLE_URL, PIHOLE_PASS and AGENT_SECRET in environment variables or edit the created {} file.", CONFIG_PATH),
                 )))
             }
         },
@@ -22,17 +32,35 @@ pub fn load_config() -> Result<Config, Box<dyn std::error::Error>> {
 }
 
 fn load_config_from_env() -> Result<Config, std::io::Error> {
-    let pihole_url = std::env::var("PIHOLE_URL").map_err(|_| {
-        std::io::Error::new(
+    let dns_backend = match std::env::var("DNS_BACKEND").unwrap_or("pihole".to_string()).as_str() {
+        "pihole" => DnsBackendKind::Pihole,
+        "hosts_file" => DnsBackendKind::HostsFile,
+        _ => return Err(std::io::Error::new(
             std::io::ErrorKind::InvalidInput,
-            "PIHOLE_URL env var is undefined",
-        )
-    })?;
+            "DNS_BACKEND must be one of pihole, hosts_file",
+        )),
+    };
+
+    // Pi-hole credentials are only required when Pi-hole is the DNS backend
+    let pihole_env = |name: &str| match std::env::var(name) {
+        Ok(value) => Ok(value),
+        Err(_) if dns_backend != DnsBackendKind::Pihole => Ok(String::new()),
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{} env var is undefined", name),
+        )),
+    };
+
+    let pihole_url = pihole_env("PIHOLE_URL")?;
+    let pihole_pass = pihole_env("PIHOLE_PASS")?;
+
+    let hosts_file_path = std::env::var("HOSTS_FILE_PATH")
+        .unwrap_or(DEFAULT_HOSTS_FILE_PATH.to_string());
 
-    let pihole_pass = std::env::var("PIHOLE_PASS").map_err(|_| {
+    let agent_secret = std::env::var("AGENT_SECRET").map_err(|_| {
         std::io::Error::new(
             std::io::ErrorKind::InvalidInput,
-            "PIHOLE_PASS env var is undefined",
+            "AGENT_SECRET env var is undefined",
         )
     })?;
 
@@ -56,12 +84,149 @@ fn load_config_from_env() -> Result<Config, std::io::Error> {
             )
         })?;
 
-    Ok(Config {
+    let database_path = std::env::var("DATABASE_PATH")
+        .unwrap_or(DEFAULT_DATABASE_PATH.to_string());
+
+    let admin_token = std::env::var("ADMIN_TOKEN").ok();
+
+    let dns_domain = std::env::var("DNS_DOMAIN").ok();
+
+    let reconcile_interval_secs = std::env::var("RECONCILE_INTERVAL_SECS")
+        .unwrap_or(DEFAULT_RECONCILE_INTERVAL_SECS.to_string())
+        .parse::<u64>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "RECONCILE_INTERVAL_SECS must be a valid number of seconds (0 to disable)",
+            )
+        })?;
+
+    let reconcile_dry_run = std::env::var("RECONCILE_DRY_RUN")
+        .unwrap_or("false".to_string())
+        .parse::<bool>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "RECONCILE_DRY_RUN must be true or false",
+            )
+        })?;
+
+    let heartbeat_interval_secs = std::env::var("HEARTBEAT_INTERVAL_SECS")
+        .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL_SECS.to_string())
+        .parse::<u64>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "HEARTBEAT_INTERVAL_SECS must be a positive number of seconds",
+            )
+        })?;
+
+    let max_heartbeat_interval_secs = std::env::var("MAX_HEARTBEAT_INTERVAL_SECS")
+        .unwrap_or(DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS.to_string())
+        .parse::<u64>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "MAX_HEARTBEAT_INTERVAL_SECS must be a number of seconds no lower than HEARTBEAT_INTERVAL_SECS",
+            )
+        })?;
+
+    let heartbeat_miss_factor = std::env::var("HEARTBEAT_MISS_FACTOR")
+        .unwrap_or(DEFAULT_HEARTBEAT_MISS_FACTOR.to_string())
+        .parse::<u32>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "HEARTBEAT_MISS_FACTOR must be a positive number of missed heartbeats",
+            )
+        })?;
+
+    let liveness_check_interval_secs = std::env::var("LIVENESS_CHECK_INTERVAL_SECS")
+        .unwrap_or(DEFAULT_LIVENESS_CHECK_INTERVAL_SECS.to_string())
+        .parse::<u64>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "LIVENESS_CHECK_INTERVAL_SECS must be a positive number of seconds",
+            )
+        })?;
+
+    let retire_after_hours = std::env::var("RETIRE_AFTER_HOURS")
+        .unwrap_or("0".to_string())
+        .parse::<u64>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "RETIRE_AFTER_HOURS must be a valid number of hours (0 to disable)",
+            )
+        })?;
+
+    let purge_after_hours = std::env::var("PURGE_AFTER_HOURS")
+        .unwrap_or(DEFAULT_PURGE_AFTER_HOURS.to_string())
+        .parse::<u64>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "PURGE_AFTER_HOURS must be a valid number of hours (0 to keep retired agents)",
+            )
+        })?;
+
+    let event_retention_days = std::env::var("EVENT_RETENTION_DAYS")
+        .unwrap_or(DEFAULT_EVENT_RETENTION_DAYS.to_string())
+        .parse::<u64>()
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "EVENT_RETENTION_DAYS must be a valid number of days (0 to keep events forever)",
+            )
+        })?;
+
+    let webhooks = match std::env::var("WEBHOOK_URL") {
+        Ok(url) => {
+            let format = match std::env::var("WEBHOOK_FORMAT").unwrap_or("generic".to_string()).as_str() {
+                "generic" => WebhookFormat::Generic,
+                "ntfy" => WebhookFormat::Ntfy,
+                "gotify" => WebhookFormat::Gotify,
+                "slack" => WebhookFormat::Slack,
+                _ => return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "WEBHOOK_FORMAT must be one of generic, ntfy, gotify, slack",
+                )),
+            };
+            let events = std::env::var("WEBHOOK_EVENTS")
+                .map(|events| events.split(',').map(|event| event.trim().to_string()).filter(|event| !event.is_empty()).collect())
+                .unwrap_or_default();
+
+            vec![WebhookConfig { url, format, events }]
+        }
+        Err(_) => Vec::new(),
+    };
+
+    let config = Config {
+        dns_backend,
         pihole_url,
         pihole_pass,
+        hosts_file_path,
+        agent_secret,
         bind_port,
         log_level,
-    })
+        database_path,
+        admin_token,
+        dns_domain,
+        reconcile_interval_secs,
+        reconcile_dry_run,
+        heartbeat_interval_secs,
+        max_heartbeat_interval_secs,
+        heartbeat_miss_factor,
+        liveness_check_interval_secs,
+        retire_after_hours,
+        purge_after_hours,
+        event_retention_days,
+        webhooks,
+    };
+    validate_liveness(&config)?;
+
+    Ok(config)
 }
 
 fn load_config_from_json() -> Result<Config, Box<dyn std::error::Error>> {
@@ -70,13 +235,23 @@ fn load_config_from_json() -> Result<Config, Box<dyn std::error::Error>> {
         let reader = std::io::BufReader::new(file);
         let config: Config = serde_json::from_reader(reader)?;
         let default_config = Config::default();
-        if config.pihole_url == default_config.pihole_url || config.pihole_pass == default_config.pihole_pass {
+        if config.dns_backend == DnsBackendKind::Pihole
+            && (config.pihole_url == default_config.pihole_url || config.pihole_pass == default_config.pihole_pass) {
             return Err(Box::new(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 format!("Set a valid PIHOLE_URL and PIHOLE_PASS in {}", CONFIG_PATH),
             )));
         }
-        
+
+        if config.agent_secret.is_empty() || config.agent_secret == default_config.agent_secret {
+            return Err(Box::new(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Set a valid AGENT_SECRET in {}", CONFIG_PATH),
+            )));
+        }
+
+        validate_liveness(&config)?;
+
         return Ok(config);
     }
     
@@ -87,6 +262,27 @@ fn load_config_from_json() -> Result<Config, Box<dyn std::error::Error>> {
     )))
 }
 
+/// Checks the liveness settings the same way whichever source they came from, a zero interval or
+/// factor would either spin the liveness monitor or mark every agent offline on its first check.
+fn validate_liveness(config: &Config) -> Result<(), std::io::Error> {
+    let invalid = |message: &str| Err(std::io::Error::new(std::io::ErrorKind::InvalidInput, message.to_string()));
+
+    if config.heartbeat_interval_secs == 0 {
+        return invalid("HEARTBEAT_INTERVAL_SECS must be a positive number of seconds");
+    }
+    if config.max_heartbeat_interval_secs < config.heartbeat_interval_secs {
+        return invalid("MAX_HEARTBEAT_INTERVAL_SECS must be a number of seconds no lower than HEARTBEAT_INTERVAL_SECS");
+    }
+    if config.heartbeat_miss_factor == 0 {
+        return invalid("HEARTBEAT_MISS_FACTOR must be a positive number of missed heartbeats");
+    }
+    if config.liveness_check_interval_secs == 0 {
+        return invalid("LIVENESS_CHECK_INTERVAL_SECS must be a positive number of seconds");
+    }
+
+    Ok(())
+}
+
 fn create_default_config_file() -> Result<(), Box<dyn std::error::Error>> {
     let default_config = Config::default();
     let file = std::fs::File::create(CONFIG_PATH)?;
@@ -95,22 +291,147 @@ fn create_default_config_file() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum DnsBackendKind {
+    #[default]
+    Pihole,
+    HostsFile,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default, Debug)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum WebhookFormat {
+    #[default]
+    Generic,
+    Ntfy,
+    Gotify,
+    Slack,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct WebhookConfig {
+    pub url: String,
+    #[serde(default)]
+    pub format: WebhookFormat,
+    /// Event types to send, e.g. `agent_offline`, all of them when empty.
+    #[serde(default)]
+    pub events: Vec<String>,
+}
+
+impl WebhookConfig {
+    pub(crate) fn wants(&self, event: &Event) -> bool {
+        self.events.is_empty() || self.events.iter().any(|kind| kind == event.kind())
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 pub(crate) struct Config {
+    #[serde(default)]
+    pub dns_backend: DnsBackendKind,
     pub pihole_url: String,
     pub pihole_pass: String,
+    #[serde(default = "default_hosts_file_path")]
+    pub hosts_file_path: String,
+    #[serde(default)]
+    pub agent_secret: String,
     pub bind_port: u16,
     #[serde(with = "logging")]
     pub log_level: LevelFilter,
+    #[serde(default = "default_database_path")]
+    pub database_path: String,
+    #[serde(default)]
+    pub admin_token: Option<String>,
+    /// Suffix appended to single-label agent names and aliases, e.g. `home.arpa`.
+    #[serde(default)]
+    pub dns_domain: Option<String>,
+    #[serde(default = "default_reconcile_interval_secs")]
+    pub reconcile_interval_secs: u64,
+    #[serde(default)]
+    pub reconcile_dry_run: bool,
+    /// Heartbeat interval handed to agents that don't ask for a specific one.
+    #[serde(default = "default_heartbeat_interval_secs")]
+    pub heartbeat_interval_secs: u64,
+    /// Longest heartbeat interval an agent may ask for.
+    #[serde(default = "default_max_heartbeat_interval_secs")]
+    pub max_heartbeat_interval_secs: u64,
+    /// Number of heartbeat intervals without news after which an agent is offline.
+    #[serde(default = "default_heartbeat_miss_factor")]
+    pub heartbeat_miss_factor: u32,
+    #[serde(default = "default_liveness_check_interval_secs")]
+    pub liveness_check_interval_secs: u64,
+    /// Hours offline after which an agent's DNS records are removed and it is retired, 0 to never retire agents.
+    #[serde(default)]
+    pub retire_after_hours: u64,
+    /// Hours after retirement at which an agent is forgotten, 0 to keep retired agents.
+    #[serde(default = "default_purge_after_hours")]
+    pub purge_after_hours: u64,
+    /// Days the event history is kept, 0 to keep it forever.
+    #[serde(default = "default_event_retention_days")]
+    pub event_retention_days: u64,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+}
+
+fn default_hosts_file_path() -> String {
+    DEFAULT_HOSTS_FILE_PATH.to_string()
+}
+
+fn default_database_path() -> String {
+    DEFAULT_DATABASE_PATH.to_string()
+}
+
+fn default_reconcile_interval_secs() -> u64 {
+    DEFAULT_RECONCILE_INTERVAL_SECS
+}
+
+fn default_heartbeat_interval_secs() -> u64 {
+    DEFAULT_HEARTBEAT_INTERVAL_SECS
+}
+
+fn default_max_heartbeat_interval_secs() -> u64 {
+    DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS
+}
+
+fn default_heartbeat_miss_factor() -> u32 {
+    DEFAULT_HEARTBEAT_MISS_FACTOR
+}
+
+fn default_liveness_check_interval_secs() -> u64 {
+    DEFAULT_LIVENESS_CHECK_INTERVAL_SECS
+}
+
+fn default_purge_after_hours() -> u64 {
+    DEFAULT_PURGE_AFTER_HOURS
+}
+
+fn default_event_retention_days() -> u64 {
+    DEFAULT_EVENT_RETENTION_DAYS
 }
 
 impl Default for Config {
     fn default() -> Self {
         Config {
+            dns_backend: DnsBackendKind::Pihole,
             pihole_url: "pihole_url".to_string(),
             pihole_pass: "pihole_pass".to_string(),
+            hosts_file_path: DEFAULT_HOSTS_FILE_PATH.to_string(),
+            agent_secret: "agent_secret".to_string(),
             bind_port: DEFAULT_BIND_PORT,
-            log_level: LevelFilter::INFO
+            log_level: LevelFilter::INFO,
+            database_path: DEFAULT_DATABASE_PATH.to_string(),
+            admin_token: None,
+            dns_domain: None,
+            reconcile_interval_secs: DEFAULT_RECONCILE_INTERVAL_SECS,
+            reconcile_dry_run: false,
+            heartbeat_interval_secs: DEFAULT_HEARTBEAT_INTERVAL_SECS,
+            max_heartbeat_interval_secs: DEFAULT_MAX_HEARTBEAT_INTERVAL_SECS,
+            heartbeat_miss_factor: DEFAULT_HEARTBEAT_MISS_FACTOR,
+            liveness_check_interval_secs: DEFAULT_LIVENESS_CHECK_INTERVAL_SECS,
+            retire_after_hours: 0,
+            purge_after_hours: DEFAULT_PURGE_AFTER_HOURS,
+            event_retention_days: DEFAULT_EVENT_RETENTION_DAYS,
+            webhooks: Vec::new(),
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,45 @@
+use crate::model::dns_record::DnsRecord;
+use core::logging::{error, warn};
+use std::{future::Future, pin::Pin};
+
+pub(crate) type DnsResult<T> = Result<T, Box<dyn std::error::Error>>;
+pub(crate) type DnsFuture<'a, T> = Pin<Box<dyn Future<Output = DnsResult<T>> + Send + 'a>>;
+
+/// Local DNS server PiWatch publishes agent addresses to.
+///
+/// Implementations must treat adding an existing record and removing a missing one as success.
+pub(crate) trait DnsBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn upsert_host<'a>(&'a self, hostname: &'a str, ip: &'a str) -> DnsFuture<'a, ()>;
+
+    fn remove_host<'a>(&'a self, hostname: &'a str, ip: &'a str) -> DnsFuture<'a, ()>;
+
+    fn list_hosts(&self) -> DnsFuture<'_, Vec<DnsRecord>>;
+
+    /// Points `alias` at `target` with a CNAME record.
+    fn upsert_cname<'a>(&'a self, alias: &'a str, target: &'a str) -> DnsFuture<'a, ()>;
+
+    fn remove_cname<'a>(&'a self, alias: &'a str, target: &'a str) -> DnsFuture<'a, ()>;
+
+    fn health(&self) -> DnsFuture<'_, ()>;
+
+    /// Moves `hostname` from `old_ip` to `new_ip`, restoring the old mapping if the new one can't be added.
+    fn replace_host<'a>(&'a self, hostname: &'a str, old_ip: &'a str, new_ip: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(async move {
+            self.remove_host(hostname, old_ip).await?;
+
+            let upsert_error = match self.upsert_host(hostname, new_ip).await {
+                Ok(_) => return Ok(()),
+                Err(e) => e.to_string(),
+            };
+
+            warn!("Failed to add {} for {}, restoring {}", new_ip, hostname, old_ip);
+            if let Err(e) = self.upsert_host(hostname, old_ip).await {
+                error!("Failed to restore {} for {}: {}", old_ip, hostname, e);
+            }
+
+            Err(upsert_error.into())
+        })
+    }
+}
@@ -0,0 +1,129 @@
+use crate::dns::backend::{DnsBackend, DnsFuture, DnsResult};
+use crate::model::dns_record::DnsRecord;
+use core::logging::info;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::sync::Mutex;
+
+/// Keeps records in a dedicated `/etc/hosts`-style file, e.g. one loaded by dnsmasq through `addn-hosts`.
+///
+/// The whole file is owned by PiWatch and rewritten on every change.
+pub(crate) struct HostsFileBackend {
+    path: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl HostsFileBackend {
+    pub(crate) fn new(path: &str) -> Self {
+        Self {
+            path: PathBuf::from(path),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    fn read_records(&self) -> DnsResult<Vec<DnsRecord>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = std::fs::read_to_string(&self.path)?;
+        let records = content
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let ip = parts.next()?;
+                Some(parts.map(move |hostname| DnsRecord {
+                    hostname: hostname.to_string(),
+                    ip: ip.to_string(),
+                }))
+            })
+            .flatten()
+            .collect();
+
+        Ok(records)
+    }
+
+    // Written and synced to a sibling temp file first so neither readers nor a power loss leave a half-written hosts file
+    fn write_records(&self, records: &[DnsRecord]) -> DnsResult<()> {
+        let mut content = String::from("# Managed by PiWatch\n");
+        for record in records {
+            content.push_str(&format!("{} {}\n", record.ip, record.hostname));
+        }
+
+        let mut tmp_path = self.path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+
+        let mut file = std::fs::File::create(&tmp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp_path, &self.path)?;
+
+        Ok(())
+    }
+}
+
+impl DnsBackend for HostsFileBackend {
+    fn name(&self) -> &'static str {
+        "hosts_file"
+    }
+
+    fn upsert_host<'a>(&'a self, hostname: &'a str, ip: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(async move {
+            let _guard = self.write_lock.lock().await;
+            let mut records = self.read_records()?;
+            let record = DnsRecord {
+                hostname: hostname.to_string(),
+                ip: ip.to_string(),
+            };
+
+            if !records.contains(&record) {
+                records.push(record);
+                self.write_records(&records)?;
+                info!("Successfully updated IP for {} to {}", hostname, ip);
+            }
+
+            Ok(())
+        })
+    }
+
+    fn remove_host<'a>(&'a self, hostname: &'a str, ip: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(async move {
+            let _guard = self.write_lock.lock().await;
+            let mut records = self.read_records()?;
+            let count = records.len();
+            records.retain(|record| record.hostname != hostname || record.ip != ip);
+
+            if records.len() != count {
+                self.write_records(&records)?;
+                info!("Successfully deleted IP for {}", hostname);
+            }
+
+            Ok(())
+        })
+    }
+
+    fn list_hosts(&self) -> DnsFuture<'_, Vec<DnsRecord>> {
+        Box::pin(async move { self.read_records() })
+    }
+
+    // Hosts files map addresses to names, there is no way to express an alias of another name
+    fn upsert_cname<'a>(&'a self, alias: &'a str, _target: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(async move { Err(format!("Cannot add CNAME {}, hosts files do not support CNAME records", alias).into()) })
+    }
+
+    fn remove_cname<'a>(&'a self, _alias: &'a str, _target: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(async move { Ok(()) })
+    }
+
+    fn health(&self) -> DnsFuture<'_, ()> {
+        Box::pin(async move {
+            let dir = self.path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            if std::fs::metadata(dir)?.permissions().readonly() {
+                return Err(format!("{} is not writable", dir.display()).into());
+            }
+
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,3 @@
+pub mod backend;
+pub mod hosts_file;
+pub mod naming;
@@ -0,0 +1,88 @@
+const MAX_LABEL_LEN: usize = 63;
+const MAX_NAME_LEN: usize = 253;
+
+/// Turns agent hostnames and aliases into the DNS names PiWatch publishes.
+pub(crate) struct NamingPolicy {
+    domain: Option<String>,
+}
+
+impl NamingPolicy {
+    /// `domain` is appended to single-label names, e.g. `home.arpa` turns `pi4` into `pi4.home.arpa`.
+    pub(crate) fn new(domain: Option<&str>) -> Self {
+        Self {
+            domain: domain
+                .map(|domain| domain.trim_matches('.').to_lowercase())
+                .filter(|domain| !domain.is_empty()),
+        }
+    }
+
+    /// The DNS name for an agent hostname or alias, or `None` if nothing valid is left after sanitising.
+    ///
+    /// Each label is lowercased and characters outside `a-z`, `0-9` and `-` become `-`. Names that
+    /// already contain a dot, like `grafana.lan`, are taken as fully qualified.
+    pub(crate) fn dns_name(&self, name: &str) -> Option<String> {
+        let labels: Vec<String> = name
+            .trim_matches('.')
+            .split('.')
+            .map(sanitize_label)
+            .collect();
+
+        if labels.iter().any(String::is_empty) {
+            return None;
+        }
+
+        let mut dns_name = labels.join(".");
+        if let (1, Some(domain)) = (labels.len(), &self.domain) {
+            dns_name = format!("{}.{}", dns_name, domain);
+        }
+
+        (dns_name.len() <= MAX_NAME_LEN).then_some(dns_name)
+    }
+}
+
+fn sanitize_label(label: &str) -> String {
+    let label: String = label
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '-' })
+        .collect();
+
+    let label = label.trim_matches('-');
+    label[..label.len().min(MAX_LABEL_LEN)].trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appends_the_domain_to_single_labels() {
+        let policy = NamingPolicy::new(Some(".Home.Arpa."));
+
+        assert_eq!(policy.dns_name("pi4").as_deref(), Some("pi4.home.arpa"));
+        assert_eq!(policy.dns_name("grafana.lan").as_deref(), Some("grafana.lan"));
+        assert_eq!(NamingPolicy::new(None).dns_name("pi4").as_deref(), Some("pi4"));
+        assert_eq!(NamingPolicy::new(Some("")).dns_name("pi4").as_deref(), Some("pi4"));
+    }
+
+    #[test]
+    fn sanitizes_labels() {
+        let policy = NamingPolicy::new(None);
+
+        assert_eq!(policy.dns_name("Living Room_Pi").as_deref(), Some("living-room-pi"));
+        assert_eq!(policy.dns_name("-pi-").as_deref(), Some("pi"));
+        assert_eq!(policy.dns_name("pi.").as_deref(), Some("pi"));
+        assert_eq!(policy.dns_name(&"a".repeat(70)), Some("a".repeat(MAX_LABEL_LEN)));
+    }
+
+    #[test]
+    fn rejects_names_with_nothing_left() {
+        let policy = NamingPolicy::new(None);
+
+        assert_eq!(policy.dns_name(""), None);
+        assert_eq!(policy.dns_name("__"), None);
+        assert_eq!(policy.dns_name("pi..lan"), None);
+        let long = vec!["a".repeat(MAX_LABEL_LEN); 5].join(".");
+        assert_eq!(policy.dns_name(&long), None);
+    }
+}
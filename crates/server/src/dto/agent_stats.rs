@@ -0,0 +1,9 @@
+use serde::{Serialize};
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct AgentStats {
+    pub agents_total: usize,
+    pub agents_online: usize,
+    pub agents_offline: usize,
+}
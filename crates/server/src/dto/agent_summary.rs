@@ -1,14 +1,43 @@
+use core::dto::network_interface::NetworkInterface;
 use serde::{Serialize};
-use std::{
-    time::{SystemTime},
-};
+use utoipa::ToSchema;
+use crate::dto::timestamp::Timestamp;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub(crate) struct AgentSummary {
+    pub id: String,
     pub hostname: String,
+    pub dns_name: Option<String>,
+    pub aliases: Vec<String>,
     pub agent_version: String,
-    pub ipv4: String,
+    pub ipv4: Option<String>,
+    pub ipv6: Vec<String>,
+    pub interfaces: Vec<NetworkInterface>,
+    /// Every address on the agent's interfaces, including the ones not published in DNS.
+    pub addresses: Vec<AgentAddress>,
     pub online: bool,
-    pub registered_at: SystemTime,
+    #[schema(value_type = String, format = DateTime)]
+    pub registered_at: Timestamp,
+    /// Last registration or heartbeat.
+    #[schema(value_type = String, format = DateTime)]
+    pub last_seen_at: Timestamp,
     pub last_seen_sec: u64,
-}
\ No newline at end of file
+    /// When the addresses published in DNS last changed.
+    #[schema(value_type = String, format = DateTime)]
+    pub last_ip_change_at: Timestamp,
+    pub heartbeat_interval_sec: u64,
+    /// Set once the agent's DNS records were removed for being offline too long.
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub retired_at: Option<Timestamp>,
+    /// Set while the agent is down after announcing it was stopping.
+    #[schema(value_type = Option<String>, format = DateTime)]
+    pub stopped_at: Option<Timestamp>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct AgentAddress {
+    pub ip: String,
+    pub interface: String,
+    /// Whether the address has an A or AAAA record.
+    pub published: bool,
+}
@@ -0,0 +1,10 @@
+use crate::model::event::RecordedEvent;
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct EventPage {
+    pub events: Vec<RecordedEvent>,
+    /// Pass as `after` to get the next page, absent on the last page.
+    pub next_after: Option<i64>,
+}
@@ -1 +1,4 @@
-pub mod agent_summary;
\ No newline at end of file
+pub mod agent_summary;
+pub mod agent_stats;
+pub mod event_page;
+pub mod timestamp;
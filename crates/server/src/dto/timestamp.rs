@@ -0,0 +1,70 @@
+use axum::{
+    extract::FromRequestParts,
+    http::{header::ACCEPT, request::Parts},
+};
+use serde::{Serialize, Serializer};
+use std::{convert::Infallible, time::SystemTime};
+
+/// Media type clients send in `Accept` to keep the timestamp shape from before RFC 3339.
+pub(crate) const LEGACY_MEDIA_TYPE: &str = "application/vnd.piwatch.legacy+json";
+
+/// How timestamps are written in a response, picked from the request's `Accept` header.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum TimestampFormat {
+    /// `2026-10-18T12:04:10Z`
+    Rfc3339,
+    /// serde's `{"secs_since_epoch":..,"nanos_since_epoch":..}`
+    Legacy,
+}
+
+impl TimestampFormat {
+    pub(crate) fn apply(self, at: SystemTime) -> Timestamp {
+        Timestamp { at, format: self }
+    }
+}
+
+impl Timestamp {
+    pub(crate) fn in_format(self, format: TimestampFormat) -> Self {
+        format.apply(self.at)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for TimestampFormat {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let legacy = parts.headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|media_type| media_type.split(';').next().unwrap_or_default().trim().eq_ignore_ascii_case(LEGACY_MEDIA_TYPE));
+
+        Ok(match legacy {
+            true => TimestampFormat::Legacy,
+            false => TimestampFormat::Rfc3339,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Timestamp {
+    at: SystemTime,
+    format: TimestampFormat,
+}
+
+/// RFC 3339 unless the response asks otherwise.
+impl From<SystemTime> for Timestamp {
+    fn from(at: SystemTime) -> Self {
+        TimestampFormat::Rfc3339.apply(at)
+    }
+}
+
+impl Serialize for Timestamp {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.format {
+            TimestampFormat::Rfc3339 => serializer.collect_str(&humantime::format_rfc3339_seconds(self.at)),
+            TimestampFormat::Legacy => self.at.serialize(serializer),
+        }
+    }
+}
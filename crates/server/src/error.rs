@@ -0,0 +1,68 @@
+use axum::{
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use core::dto::error_body::{ErrorBody, HOSTNAME_CONFLICT, UNKNOWN_AGENT};
+
+pub(crate) enum ApiError {
+    Validation(String),
+    Unauthorized(String),
+    PayloadTooLarge,
+    UnknownAgent(String),
+    HostnameConflict { hostname: String, owner: String },
+    Dns(String),
+    Storage(String),
+}
+
+impl ApiError {
+    fn status(&self) -> StatusCode {
+        match self {
+            ApiError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::UnknownAgent(_) => StatusCode::NOT_FOUND,
+            ApiError::HostnameConflict { .. } => StatusCode::CONFLICT,
+            ApiError::Dns(_) => StatusCode::BAD_GATEWAY,
+            ApiError::Storage(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            ApiError::Validation(_) => "validation_failed",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::PayloadTooLarge => "payload_too_large",
+            ApiError::UnknownAgent(_) => UNKNOWN_AGENT,
+            ApiError::HostnameConflict { .. } => HOSTNAME_CONFLICT,
+            ApiError::Dns(_) => "dns_backend_unavailable",
+            ApiError::Storage(_) => "storage_error",
+        }
+    }
+
+    fn message(&self) -> String {
+        match self {
+            ApiError::Validation(msg) | ApiError::Unauthorized(msg) | ApiError::Dns(msg) | ApiError::Storage(msg) => msg.clone(),
+            ApiError::PayloadTooLarge => "Request body too large".to_string(),
+            ApiError::UnknownAgent(hostname) => format!("Unknown agent {}", hostname),
+            ApiError::HostnameConflict { hostname, owner } => format!("Hostname {} is already used by agent {}", hostname, owner),
+        }
+    }
+
+    // Only upstream failures are worth retrying, the others will fail the same way again.
+    fn retryable(&self) -> bool {
+        matches!(self, ApiError::Dns(_))
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorBody {
+            code: self.code().to_string(),
+            message: self.message(),
+            retryable: self.retryable(),
+        };
+
+        (self.status(), Json(body)).into_response()
+    }
+}
@@ -1,70 +1,429 @@
-use core::dto::{register_payload::RegisterPayload, update_id::IpUpdatePayload};
+use core::dto::{error_body::ErrorBody, register_payload::RegisterPayload, register_response::RegisterResponse, update_id::IpUpdatePayload};
 use crate::AppState;
-use crate::model::state::AgentState;
+use crate::error::ApiError;
+use crate::model::{dns_record::DnsRecord, event::Event, state::{agent_key, published_addresses, unnamed_interface, AgentState}};
+use core::dto::network_interface::NetworkInterface;
 use axum::{
     extract::State,
+    http::StatusCode,
     Json,
 };
 use core::logging::{error, info, warn};
-use std::time::{SystemTime, Instant};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::SystemTime;
 
-// TODO: return proper response
-pub(crate) async fn register(State(state): State<AppState>, Json(req): Json<RegisterPayload>) {
-    if req.ipv4.is_none() {
-        warn!("REGISTER received with no IPv4 for hostname {}", req.hostname);
-        return;
+#[utoipa::path(
+    post,
+    path = "/register",
+    request_body = RegisterPayload,
+    responses(
+        (status = 200, description = "Agent registered and its addresses published", body = RegisterResponse),
+        (status = 401, description = "Missing or invalid request signature", body = ErrorBody),
+        (status = 409, description = "Hostname already used by another agent", body = ErrorBody),
+        (status = 422, description = "Invalid addresses", body = ErrorBody),
+        (status = 502, description = "DNS backend failure", body = ErrorBody),
+    ),
+    security(("agent_signature" = [])),
+)]
+pub(crate) async fn register(State(state): State<AppState>, Json(req): Json<RegisterPayload>) -> Result<Json<RegisterResponse>, ApiError> {
+    let id = agent_key(req.agent_id.as_deref(), &req.hostname);
+    let dns_name = dns_name(&state, &req.hostname)?;
+
+    let mut aliases: Vec<String> = Vec::new();
+    for alias in &req.aliases {
+        match state.naming.dns_name(alias) {
+            Some(name) if name != dns_name && !aliases.contains(&name) => aliases.push(name),
+            Some(_) => {}
+            None => warn!("Ignoring invalid alias {} for hostname={}", alias, req.hostname),
+        }
     }
-    
-    let ip = req.ipv4.unwrap();
-    
-    if let Err(e) = state.pihole_client.put_ip(&req.hostname, &ip).await {
-        error!("Failed to register IP for hostname={}: {}", req.hostname, e);
-        return;
+
+    let interfaces = match req.interfaces.is_empty() {
+        true => vec![unnamed_interface(req.ipv4, req.ipv6)],
+        false => req.interfaces,
+    };
+
+    for interface in &interfaces {
+        validate_ipv4(interface.ipv4.as_deref())?;
+        for ip in &interface.ipv6 {
+            validate_ipv6(Some(ip))?;
+        }
+    }
+
+    let (ipv4, ipv6) = published_addresses(&interfaces);
+    if ipv4.is_none() && ipv6.is_empty() {
+        warn!("REGISTER received with no IP address for hostname {}", req.hostname);
+        return Err(ApiError::Validation("At least one IPv4 or IPv6 address is required".to_string()));
+    }
+
+    let registration = state.registrations.lock().await;
+    claim_hostname(&state, &id, &req.hostname, &dns_name)?;
+
+    let ips: Vec<String> = ipv4.iter().chain(ipv6.iter()).cloned().collect();
+    let previous = state.agents.get(&id).map(|agent| (
+        agent.hostname.clone(),
+        agent.ipv4.iter().chain(agent.ipv6.iter()).cloned().collect::<Vec<_>>(),
+        agent.aliases.clone(),
+        agent.reported_offline && agent.stopped_at.is_none(),
+        agent.last_ip_change_at,
+    ));
+
+    let mut events = vec![Event::AgentRegistered {
+        id: id.clone(),
+        hostname: req.hostname.clone(),
+        addresses: ips.clone(),
+        first: previous.is_none(),
+    }];
+    if let Some((_, previous_ips, _, reported_offline, _)) = &previous {
+        if *reported_offline {
+            events.push(Event::AgentOnline { id: id.clone(), hostname: req.hostname.clone() });
+        }
+        if !same_addresses(previous_ips, &ips) {
+            events.push(Event::IpChanged { id: id.clone(), hostname: req.hostname.clone(), old: previous_ips.clone(), new: ips.clone() });
+        }
+    }
+
+    let now = SystemTime::now();
+    let last_ip_change_at = match &previous {
+        Some((_, previous_ips, _, _, changed_at)) if same_addresses(previous_ips, &ips) => *changed_at,
+        _ => now,
+    };
+
+    let (previous_name, stale_ips, stale_aliases) = match previous {
+        // A renamed agent leaves nothing behind under its old name
+        Some((hostname, previous_ips, previous_aliases, ..)) if hostname != req.hostname => {
+            info!("Agent {} renamed from {} to {}", id, hostname, req.hostname);
+            if let Err(e) = state.store.set_dns_records(&hostname, &[]) {
+                error!("Failed to forget DNS records for hostname={}: {}", hostname, e);
+            }
+            (state.naming.dns_name(&hostname), previous_ips, previous_aliases)
+        }
+        Some((_, previous_ips, previous_aliases, ..)) => (
+            Some(dns_name.clone()),
+            previous_ips.into_iter().filter(|ip| !ips.contains(ip)).collect(),
+            previous_aliases.into_iter().filter(|alias| !aliases.contains(alias)).collect(),
+        ),
+        None => (None, Vec::new(), Vec::new()),
+    };
+
+    if let Err(e) = state.store.set_dns_records(&req.hostname, &ips) {
+        error!("Failed to persist DNS records for hostname={}: {}", req.hostname, e);
+    }
+
+    // A records first, then one AAAA record per published IPv6 address
+    for ip in &ips {
+        if let Err(e) = state.dns.upsert_host(&dns_name, ip).await {
+            error!("Failed to register IP {} for hostname={}: {}", ip, req.hostname, e);
+            return Err(dns_error(&state, format!("Failed to register IP {}: {}", ip, e)));
+        };
+    }
+    state.events.dns_sync(state.dns.name(), Ok(()));
+
+    // Addresses from a previous registration that the agent no longer has
+    if let Some(previous_name) = &previous_name {
+        for ip in &stale_ips {
+            if let Err(e) = state.dns.remove_host(previous_name, ip).await {
+                warn!("Failed to remove stale IP {} for hostname={}: {}", ip, req.hostname, e);
+            }
+        }
+    }
+
+    // Aliases are a convenience on top of the host records, failing to publish one doesn't fail the registration
+    for alias in &aliases {
+        if let Err(e) = state.dns.upsert_cname(alias, &dns_name).await {
+            warn!("Failed to add alias {} for hostname={}: {}", alias, req.hostname, e);
+        }
+    }
+
+    if let Some(previous_name) = &previous_name {
+        for alias in &stale_aliases {
+            if let Err(e) = state.dns.remove_cname(alias, previous_name).await {
+                warn!("Failed to remove stale alias {} for hostname={}: {}", alias, req.hostname, e);
+            }
+        }
+    }
+
+    let heartbeat_interval = state.liveness.heartbeat_interval(req.heartbeat_interval_secs);
+    let agent = AgentState {
+        id: id.clone(),
+        hostname: req.hostname.to_string(),
+        agent_version: req.agent_version,
+        ipv4,
+        ipv6,
+        interfaces,
+        aliases,
+        registered_at: now,
+        last_seen_at: now,
+        last_ip_change_at,
+        heartbeat_interval: Some(heartbeat_interval),
+        retired_at: None,
+        stopped_at: None,
+        reported_offline: false,
+    };
+
+    if let Err(e) = state.store.save_agent(&agent) {
+        error!("Failed to persist agent hostname={}: {}", req.hostname, e);
+    }
+
+    state.agents.insert(id.clone(), agent);
+    drop(registration);
+
+    for event in events {
+        state.events.record(event);
+    }
+
+    info!(
+        "REGISTER hostname={} id={} dns_name={} heartbeat_interval={}s",
+        req.hostname, id, dns_name, heartbeat_interval.as_secs(),
+    );
+    Ok(Json(RegisterResponse {
+        heartbeat_interval_secs: heartbeat_interval.as_secs(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/update",
+    request_body = IpUpdatePayload,
+    responses(
+        (status = 200, description = "Address change applied"),
+        (status = 401, description = "Missing or invalid request signature", body = ErrorBody),
+        (status = 404, description = "Unknown agent", body = ErrorBody),
+        (status = 422, description = "Invalid address or event", body = ErrorBody),
+        (status = 502, description = "DNS backend failure", body = ErrorBody),
+    ),
+    security(("agent_signature" = [])),
+)]
+pub(crate) async fn update_ip(State(state): State<AppState>, Json(req): Json<IpUpdatePayload>) -> Result<StatusCode, ApiError> {
+    let id = agent_key(req.agent_id.as_deref(), &req.hostname);
+    let Some((hostname, mut interfaces)) = state.agents
+        .get(&id)
+        .filter(|agent| agent.retired_at.is_none())
+        .map(|agent| (agent.hostname.clone(), agent.interfaces.clone())) else {
+        warn!("UPDATE received from unknown node {} id={}", req.hostname, id);
+        return Err(ApiError::UnknownAgent(req.hostname));
     };
 
-    // TODO: move to DB
-    state.agents.insert(
-        req.hostname.to_string(),
-        AgentState {
-            hostname: req.hostname.to_string(),
-            agent_version: req.agent_version,
-            ipv4: ip,
-            registered_at: SystemTime::now(),
-            last_seen: Instant::now(),
-        },
+    validate_ipv4(req.ipv4.as_deref())?;
+    validate_ipv6(req.ipv6.as_deref())?;
+    if !matches!(req.event.as_str(), "add" | "del") {
+        warn!("Skipping update... unknown event");
+        return Err(ApiError::Validation(format!("Unknown event {}", req.event)));
+    }
+
+    let is_ipv4 = req.ipv4.is_some();
+    let Some(ip) = req.ipv4.or(req.ipv6) else {
+        warn!("UPDATE received with no IP address for hostname {}", req.hostname);
+        return Err(ApiError::Validation("An IPv4 or IPv6 address is required".to_string()));
+    };
+
+    let dns_name = dns_name(&state, &hostname)?;
+    let interface = req.interface.unwrap_or_default();
+    info!("Received IP update for hostname={} interface={} event={} ip={}", req.hostname, interface, req.event, ip);
+
+    state.events.record(Event::IpReported {
+        id: id.clone(),
+        hostname: hostname.clone(),
+        interface: interface.clone(),
+        event: req.event.clone(),
+        ip: ip.clone(),
+    });
+
+    let (old_ipv4, old_ipv6) = published_addresses(&interfaces);
+    match req.event.as_str() {
+        "add" => add_address(&mut interfaces, &interface, &ip, is_ipv4),
+        // "del", addresses are unique to an interface so remove it wherever it is in case the names drifted
+        _ => interfaces.iter_mut().for_each(|watched| {
+            if watched.ipv4.as_deref() == Some(ip.as_str()) {
+                watched.ipv4 = None;
+            }
+            watched.ipv6.retain(|v6| *v6 != ip);
+        }),
+    }
+    let (new_ipv4, new_ipv6) = published_addresses(&interfaces);
+
+    // A host has a single A record, so a new primary IPv4 replaces the previous one even if
+    // its "del" event never made it here. IPv6 addresses legitimately coexist.
+    let result = match (&old_ipv4, &new_ipv4) {
+        (Some(old), Some(new)) if old != new => {
+            forget_dns_record(&state, &hostname, old);
+            remember_dns_record(&state, &hostname, new);
+            state.dns.replace_host(&dns_name, old, new).await
+        }
+        (None, Some(new)) => {
+            remember_dns_record(&state, &hostname, new);
+            state.dns.upsert_host(&dns_name, new).await
+        }
+        (Some(old), None) => {
+            forget_dns_record(&state, &hostname, old);
+            state.dns.remove_host(&dns_name, old).await
+        }
+        // An "add" for an address that is already published repairs its record
+        (Some(current), Some(_)) if req.event == "add" && *current == ip => {
+            state.dns.upsert_host(&dns_name, current).await
+        }
+        _ => Ok(()),
+    }
+    .map_err(|e| e.to_string());
+    if let Err(e) = result {
+        error!("Failed to update IPv4 for hostname {}: {}", req.hostname, e);
+        return Err(dns_error(&state, format!("Failed to update IP {}: {}", ip, e)));
+    }
+
+    for added in new_ipv6.iter().filter(|v6| !old_ipv6.contains(v6) || (req.event == "add" && **v6 == ip)) {
+        remember_dns_record(&state, &hostname, added);
+        if let Err(e) = state.dns.upsert_host(&dns_name, added).await {
+            error!("Failed to add IP {} for hostname {}: {}", added, req.hostname, e);
+            return Err(dns_error(&state, format!("Failed to add IP {}: {}", added, e)));
+        }
+    }
+
+    for removed in old_ipv6.iter().filter(|v6| !new_ipv6.contains(v6)) {
+        forget_dns_record(&state, &hostname, removed);
+        if let Err(e) = state.dns.remove_host(&dns_name, removed).await {
+            error!("Failed to delete IP {} for hostname {}: {}", removed, req.hostname, e);
+            return Err(dns_error(&state, format!("Failed to delete IP {}: {}", removed, e)));
+        }
+    }
+
+    state.events.dns_sync(state.dns.name(), Ok(()));
+
+    let old: Vec<String> = old_ipv4.into_iter().chain(old_ipv6).collect();
+    let new: Vec<String> = new_ipv4.iter().chain(new_ipv6.iter()).cloned().collect();
+    let changed = !same_addresses(&old, &new);
+    update_addresses(&state, &id, |agent| {
+        agent.set_interfaces(interfaces);
+        if changed {
+            agent.last_ip_change_at = SystemTime::now();
+        }
+    });
+
+    if changed {
+        state.events.record(Event::IpChanged { id, hostname: hostname.clone(), old, new });
+    }
+
+    info!(
+        "UPDATE hostname={} interface={} event={} ip={} published_ipv4={}",
+        req.hostname, interface, req.event, ip, new_ipv4.as_deref().unwrap_or("none"),
     );
+    Ok(StatusCode::OK)
+}
+
+// Interfaces first reported through an update rank below every registered one.
+fn add_address(interfaces: &mut Vec<NetworkInterface>, name: &str, ip: &str, is_ipv4: bool) {
+    let index = match interfaces.iter().position(|watched| watched.name == name) {
+        Some(index) => index,
+        None => {
+            interfaces.push(NetworkInterface {
+                name: name.to_string(),
+                up: true,
+                ipv4: None,
+                ipv6: Vec::new(),
+            });
+            interfaces.len() - 1
+        }
+    };
 
-    info!("REGISTER hostname={}", req.hostname);
+    let watched = &mut interfaces[index];
+    if is_ipv4 {
+        watched.ipv4 = Some(ip.to_string());
+    } else if !watched.ipv6.iter().any(|v6| v6 == ip) {
+        watched.ipv6.push(ip.to_string());
+    }
 }
 
-pub(crate) async fn update_ip(State(state): State<AppState>, Json(req): Json<IpUpdatePayload>) {
-    if req.ipv4.is_none() {
-        warn!("UPDATE received with no IPv4 for hostname {}", req.hostname);
+fn update_addresses(state: &AppState, id: &str, apply: impl FnOnce(&mut AgentState)) {
+    let Some(agent) = state.agents.get_mut(id).map(|mut agent| {
+        apply(&mut agent);
+        agent.clone()
+    }) else {
         return;
+    };
+
+    if let Err(e) = state.store.save_agent(&agent) {
+        error!("Failed to persist agent hostname={}: {}", agent.hostname, e);
     }
+}
 
-    let ip = req.ipv4.unwrap();
+/// Makes sure no other agent publishes `dns_name`, retired agents no longer hold a name.
+///
+/// Agents registered before they reported an ID are keyed by hostname, the first registration
+/// with an ID under the same hostname is that agent after an upgrade and takes the record over.
+fn claim_hostname(state: &AppState, id: &str, hostname: &str, dns_name: &str) -> Result<(), ApiError> {
+    let owner = state.agents
+        .iter()
+        .find(|agent| agent.id != id && agent.retired_at.is_none() && state.naming.dns_name(&agent.hostname).as_deref() == Some(dns_name))
+        .map(|agent| (agent.id.clone(), agent.is_legacy() && agent.hostname == hostname));
 
-    info!("Received IP update for hostname={} event={} ip={}", req.hostname, req.event, ip);
-    if req.event == "add" {
-        if let Err(e) = state.pihole_client.put_ip(&req.hostname, &ip).await {
-            error!("Failed to update IP for hostname {}: {}", req.hostname, e);
-            return;
-        };
+    match owner {
+        None => Ok(()),
+        Some((legacy_id, true)) => {
+            info!("Agent {} takes over the record of hostname={} registered without an ID", id, hostname);
+            if let Some((_, legacy)) = state.agents.remove(&legacy_id) {
+                state.agents.insert(id.to_string(), AgentState { id: id.to_string(), ..legacy });
+            }
+            if let Err(e) = state.store.delete_agent(&legacy_id) {
+                error!("Failed to delete legacy agent record hostname={}: {}", hostname, e);
+            }
 
-        info!("UPDATE hostname={} event={} ip={}", req.hostname, req.event, ip);
-        return;
+            Ok(())
+        }
+        Some((owner, false)) => {
+            warn!("Hostname collision: agent {} registered as {} which agent {} already uses", id, hostname, owner);
+            Err(ApiError::HostnameConflict { hostname: hostname.to_string(), owner })
+        }
     }
+}
 
-    if req.event == "del" {
-        if let Err(e) = state.pihole_client.delete_ip(&req.hostname, &ip).await {
-            error!("Failed to delete IP for hostname {}: {}", req.hostname, e);
-            return;
-        };
-        
-        info!("DELETE hostname={} event={} ip={}", req.hostname, req.event, ip);
-        return;
+/// Whether two sets of published addresses hold the same IPs, in any order.
+fn same_addresses(a: &[String], b: &[String]) -> bool {
+    a.len() == b.len() && a.iter().all(|ip| b.contains(ip))
+}
+
+/// Reports a DNS backend failure to the notifier and turns it into the API error.
+fn dns_error(state: &AppState, message: String) -> ApiError {
+    state.events.dns_sync(state.dns.name(), Err(message.clone()));
+    ApiError::Dns(message)
+}
+
+fn remember_dns_record(state: &AppState, hostname: &str, ip: &str) {
+    let record = DnsRecord {
+        hostname: hostname.to_string(),
+        ip: ip.to_string(),
+    };
+
+    if let Err(e) = state.store.add_dns_record(&record) {
+        error!("Failed to persist DNS record for hostname={}: {}", hostname, e);
+    }
+}
+
+fn forget_dns_record(state: &AppState, hostname: &str, ip: &str) {
+    let record = DnsRecord {
+        hostname: hostname.to_string(),
+        ip: ip.to_string(),
+    };
+
+    if let Err(e) = state.store.remove_dns_record(&record) {
+        error!("Failed to forget DNS record for hostname={}: {}", hostname, e);
     }
+}
 
-    warn!("Skipping update... unknown event");
+fn dns_name(state: &AppState, hostname: &str) -> Result<String, ApiError> {
+    state.naming
+        .dns_name(hostname)
+        .ok_or_else(|| ApiError::Validation(format!("Hostname {} has no valid DNS name", hostname)))
+}
+
+fn validate_ipv4(ip: Option<&str>) -> Result<(), ApiError> {
+    match ip {
+        Some(ip) if ip.parse::<Ipv4Addr>().is_err() => Err(ApiError::Validation(format!("Invalid IPv4 address {}", ip))),
+        _ => Ok(()),
+    }
+}
+
+fn validate_ipv6(ip: Option<&str>) -> Result<(), ApiError> {
+    match ip {
+        Some(ip) if ip.parse::<Ipv6Addr>().is_err() => Err(ApiError::Validation(format!("Invalid IPv6 address {}", ip))),
+        _ => Ok(()),
+    }
 }
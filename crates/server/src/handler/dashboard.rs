@@ -0,0 +1,21 @@
+use axum::{
+    http::header::CONTENT_TYPE,
+    response::IntoResponse,
+};
+
+// Embedded so the server stays a single binary
+const INDEX_HTML: &str = include_str!("../../static/index.html");
+const APP_JS: &str = include_str!("../../static/app.js");
+const STYLE_CSS: &str = include_str!("../../static/style.css");
+
+pub(crate) async fn index() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "text/html; charset=utf-8")], INDEX_HTML)
+}
+
+pub(crate) async fn app_js() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "text/javascript; charset=utf-8")], APP_JS)
+}
+
+pub(crate) async fn style_css() -> impl IntoResponse {
+    ([(CONTENT_TYPE, "text/css; charset=utf-8")], STYLE_CSS)
+}
@@ -0,0 +1,62 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    Json,
+};
+use core::dto::{deregister_payload::DeregisterPayload, error_body::ErrorBody};
+use core::logging::{error, info, warn};
+use std::time::SystemTime;
+use crate::error::ApiError;
+use crate::liveness::reaper::withdraw_dns;
+use crate::model::{event::Event, state::{agent_key, AppState}};
+
+#[utoipa::path(
+    post,
+    path = "/deregister",
+    request_body = DeregisterPayload,
+    responses(
+        (status = 200, description = "Shutdown recorded, no offline alert will be sent"),
+        (status = 401, description = "Missing or invalid request signature", body = ErrorBody),
+        (status = 404, description = "Unknown agent", body = ErrorBody),
+        (status = 502, description = "DNS backend failure while removing the records", body = ErrorBody),
+    ),
+    security(("agent_signature" = [])),
+)]
+pub(crate) async fn deregister(State(state): State<AppState>, Json(req): Json<DeregisterPayload>) -> Result<StatusCode, ApiError> {
+    let id = agent_key(req.agent_id.as_deref(), &req.hostname);
+    let Some((hostname, ips, aliases)) = state.agents
+        .get(&id)
+        .filter(|agent| agent.retired_at.is_none())
+        .map(|agent| (
+            agent.hostname.clone(),
+            agent.ipv4.iter().chain(agent.ipv6.iter()).cloned().collect::<Vec<_>>(),
+            agent.aliases.clone(),
+        )) else {
+        warn!("DEREGISTER from unknown node {} id={}", req.hostname, id);
+        return Err(ApiError::UnknownAgent(req.hostname));
+    };
+
+    // Marked stopped before touching DNS so the liveness monitor stays quiet either way
+    let stopped = state.agents.get_mut(&id).map(|mut agent| {
+        agent.stopped_at = Some(SystemTime::now());
+        agent.reported_offline = true;
+        agent.clone()
+    });
+    if let Some(Err(e)) = stopped.map(|agent| state.store.save_agent(&agent)) {
+        error!("Failed to persist agent hostname={}: {}", hostname, e);
+    }
+
+    let withdrawn = match req.keep_dns {
+        true => Ok(()),
+        false => withdraw_dns(&state, &hostname, ips, &aliases).await,
+    };
+    if let Err(e) = withdrawn {
+        error!("Failed to remove DNS records of stopping agent hostname={}: {}", hostname, e);
+        return Err(ApiError::Dns(e));
+    }
+
+    info!("DEREGISTER hostname={} id={} keep_dns={}", hostname, id, req.keep_dns);
+    state.events.record(Event::AgentStopped { id, hostname, dns_kept: req.keep_dns });
+
+    Ok(StatusCode::OK)
+}
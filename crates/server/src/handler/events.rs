@@ -0,0 +1,95 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use core::logging::error;
+use serde::Deserialize;
+use std::time::{Duration, UNIX_EPOCH};
+use utoipa::IntoParams;
+use core::dto::error_body::ErrorBody;
+use crate::dto::{event_page::EventPage, timestamp::TimestampFormat};
+use crate::error::ApiError;
+use crate::model::state::AppState;
+use crate::storage::event_store::EventFilter;
+
+const DEFAULT_PAGE_SIZE: usize = 100;
+const MAX_PAGE_SIZE: usize = 1000;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct EventQuery {
+    /// Only events recorded at or after this Unix time
+    since: Option<u64>,
+    /// Only events of this type, e.g. `ip_changed`
+    #[serde(rename = "type")]
+    #[param(rename = "type")]
+    kind: Option<String>,
+    /// Only events after this sequence number, `next_after` of the previous page
+    after: Option<i64>,
+    /// Events per page, 100 by default and at most 1000
+    limit: Option<usize>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/events",
+    params(EventQuery, ("accept" = Option<String>, Header, description = "`application/vnd.piwatch.legacy+json` for timestamps in the pre-RFC 3339 shape")),
+    responses(
+        (status = 200, description = "Recorded events, oldest first", body = EventPage),
+        (status = 422, description = "Invalid `since`", body = ErrorBody),
+        (status = 500, description = "Event history unavailable", body = ErrorBody),
+    ),
+)]
+pub(crate) async fn list_events(
+    State(state): State<AppState>,
+    format: TimestampFormat,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<EventPage>, ApiError> {
+    page(&state, None, query, format)
+}
+
+#[utoipa::path(
+    get,
+    path = "/agents/{id}/events",
+    params(("id" = String, Path, description = "Agent ID, the hostname for agents that predate IDs"), EventQuery, ("accept" = Option<String>, Header, description = "`application/vnd.piwatch.legacy+json` for timestamps in the pre-RFC 3339 shape")),
+    responses(
+        (status = 200, description = "Events about the agent, oldest first", body = EventPage),
+        (status = 422, description = "Invalid `since`", body = ErrorBody),
+        (status = 500, description = "Event history unavailable", body = ErrorBody),
+    ),
+)]
+pub(crate) async fn agent_events(
+    State(state): State<AppState>,
+    format: TimestampFormat,
+    Path(id): Path<String>,
+    Query(query): Query<EventQuery>,
+) -> Result<Json<EventPage>, ApiError> {
+    page(&state, Some(id), query, format)
+}
+
+fn page(state: &AppState, agent_id: Option<String>, query: EventQuery, format: TimestampFormat) -> Result<Json<EventPage>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+    let since = query.since
+        .map(|secs| UNIX_EPOCH.checked_add(Duration::from_secs(secs)).ok_or(secs))
+        .transpose()
+        .map_err(|secs| ApiError::Validation(format!("since={} is out of range", secs)))?;
+    let filter = EventFilter {
+        agent_id,
+        since,
+        kind: query.kind,
+        after: query.after,
+        limit,
+    };
+
+    let events = state.events.query(&filter).map_err(|e| {
+        error!("Failed to load events: {}", e);
+        ApiError::Storage(e.to_string())
+    })?;
+    let next_after = match events.len() == limit {
+        true => events.last().map(|event| event.seq),
+        false => None,
+    };
+    let events = events.into_iter().map(|event| event.in_format(format)).collect();
+
+    Ok(Json(EventPage { events, next_after }))
+}
@@ -1,19 +1,58 @@
 use axum::{
     extract::State,
+    http::StatusCode,
     Json,
 };
-use core::logging::warn;
-use core::dto::heart_beat::Heartbeat;
-use std::time::Instant;
-use crate::model::state::AppState;
+use core::logging::{error, warn};
+use core::dto::{error_body::ErrorBody, heart_beat::Heartbeat};
+use std::time::SystemTime;
+use crate::error::ApiError;
+use crate::model::{event::Event, state::{agent_key, AppState}};
 
+#[utoipa::path(
+    post,
+    path = "/heartbeat",
+    request_body = Heartbeat,
+    responses(
+        (status = 200, description = "Heartbeat recorded"),
+        (status = 401, description = "Missing or invalid request signature", body = ErrorBody),
+        (status = 404, description = "Unknown agent, it should register again", body = ErrorBody),
+    ),
+    security(("agent_signature" = [])),
+)]
 pub(crate) async fn heartbeat(
     State(state): State<AppState>,
     Json(req): Json<Heartbeat>,
-) {
-    if let Some(mut agent) = state.agents.get_mut(&req.hostname) {
-        agent.last_seen = Instant::now();
-    } else {
-        warn!("HEARTBEAT from unknown node {}", req.hostname);
+) -> Result<StatusCode, ApiError> {
+    let id = agent_key(req.agent_id.as_deref(), &req.hostname);
+    // Retired agents register again to get their records back
+    // Updated under the guard, persisted and recorded once it's released
+    let seen = state.agents.get_mut(&id).filter(|agent| agent.retired_at.is_none()).map(|mut agent| {
+        agent.last_seen_at = SystemTime::now();
+        // Coming back from an announced stop isn't news, no offline alert went out for it
+        let back_online = agent.reported_offline && agent.stopped_at.is_none();
+        agent.reported_offline = false;
+        let was_stopped = agent.stopped_at.take().is_some();
+
+        (agent.clone(), back_online, was_stopped)
+    });
+    let Some((agent, back_online, was_stopped)) = seen else {
+        warn!("HEARTBEAT from unknown node {} id={}", req.hostname, id);
+        return Err(ApiError::UnknownAgent(req.hostname));
+    };
+
+    if back_online {
+        state.events.record(Event::AgentOnline { id: id.clone(), hostname: agent.hostname.clone() });
     }
-}
\ No newline at end of file
+    state.events.stream(Event::Heartbeat { id: id.clone(), hostname: agent.hostname.clone() });
+
+    let persisted = match was_stopped {
+        true => state.store.save_agent(&agent),
+        false => state.store.touch_agent(&id, agent.last_seen_at),
+    };
+    if let Err(e) = persisted {
+        error!("Failed to persist heartbeat for hostname={}: {}", req.hostname, e);
+    }
+
+    Ok(StatusCode::OK)
+}
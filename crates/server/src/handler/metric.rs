@@ -1,43 +1,208 @@
 use axum::{
-    extract::State,
+    extract::{Path, Query, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::IntoResponse,
     Json,
 };
-use crate::model::state::AppState;
-use crate::dto::agent_summary::AgentSummary;
+use core::dto::error_body::ErrorBody;
+use core::logging::error;
+use serde::Deserialize;
+use std::time::SystemTime;
+use utoipa::{IntoParams, ToSchema};
+use crate::error::ApiError;
+use crate::model::{state::{AgentState, AppState}, subnet::Subnet};
+use crate::dto::{agent_stats::AgentStats, agent_summary::{AgentAddress, AgentSummary}, timestamp::TimestampFormat};
 
+const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct AgentQuery {
+    /// Only online agents, or only offline ones with `false`
+    online: Option<bool>,
+    /// Only agents running this version, e.g. `0.1.0`
+    version: Option<String>,
+    /// Only agents with an address in this network, e.g. `10.0.2.0/24`
+    subnet: Option<String>,
+    #[serde(default)]
+    #[param(inline)]
+    sort: AgentSort,
+    #[serde(default)]
+    #[param(inline)]
+    order: SortOrder,
+    /// Number of agents to skip
+    #[serde(default)]
+    offset: usize,
+    /// Maximum number of agents to return, all of them by default
+    limit: Option<usize>,
+}
+
+#[derive(Deserialize, ToSchema, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AgentSort {
+    #[default]
+    Hostname,
+    LastSeen,
+}
+
+#[derive(Deserialize, ToSchema, Default, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[utoipa::path(
+    get,
+    path = "/agents",
+    params(
+        AgentQuery,
+        ("accept" = Option<String>, Header, description = "`application/vnd.piwatch.legacy+json` for timestamps in the pre-RFC 3339 shape"),
+    ),
+    responses(
+        (status = 200, description = "Known agents matching the filters", body = Vec<AgentSummary>,
+            headers(("x-total-count" = usize, description = "Number of matching agents before pagination"))),
+        (status = 422, description = "Invalid subnet", body = ErrorBody),
+    ),
+)]
 pub(crate) async fn list_agents(
     State(state): State<AppState>,
-) -> Json<Vec<AgentSummary>> {
-    let agents = state
+    format: TimestampFormat,
+    Query(query): Query<AgentQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let subnet = query.subnet
+        .as_deref()
+        .map(str::parse::<Subnet>)
+        .transpose()
+        .map_err(ApiError::Validation)?;
+
+    let mut agents: Vec<(SystemTime, AgentSummary)> = state
         .agents
         .iter()
-        .map(|entry| {
-            let last_seen = entry.last_seen.elapsed().as_secs();
-            AgentSummary {
-                hostname: entry.hostname.clone(),
-                agent_version: entry.agent_version.clone(),
-                ipv4: entry.ipv4.clone(),
-                online: last_seen < 120,
-                last_seen_sec: last_seen,
-                registered_at: entry.registered_at,
-            }
+        .map(|entry| (entry.last_seen_at, summarize(&state, &entry, format)))
+        .filter(|(_, agent)| query.online.is_none_or(|online| agent.online == online))
+        .filter(|(_, agent)| query.version.as_ref().is_none_or(|version| agent.agent_version == *version))
+        .filter(|(_, agent)| subnet.is_none_or(|subnet| {
+            agent.addresses.iter().any(|address| address.ip.parse().is_ok_and(|ip| subnet.contains(&ip)))
+        }))
+        .collect();
+
+    agents.sort_by(|(a_seen, a), (b_seen, b)| {
+        let ordering = match query.sort {
+            AgentSort::Hostname => a.hostname.cmp(&b.hostname),
+            AgentSort::LastSeen => a_seen.cmp(b_seen),
+        };
+        let ordering = ordering.then_with(|| a.id.cmp(&b.id));
+
+        match query.order {
+            SortOrder::Asc => ordering,
+            SortOrder::Desc => ordering.reverse(),
+        }
+    });
+
+    let total = agents.len();
+    let agents: Vec<AgentSummary> = agents
+        .into_iter()
+        .map(|(_, agent)| agent)
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    Ok(([(TOTAL_COUNT_HEADER, total.to_string())], Json(agents)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/agents/{id}",
+    params(
+        ("id" = String, Path, description = "Agent ID or hostname"),
+        ("accept" = Option<String>, Header, description = "`application/vnd.piwatch.legacy+json` for timestamps in the pre-RFC 3339 shape"),
+    ),
+    responses(
+        (status = 200, description = "The agent", body = AgentSummary),
+        (status = 404, description = "Unknown agent", body = ErrorBody),
+    ),
+)]
+pub(crate) async fn get_agent(
+    State(state): State<AppState>,
+    format: TimestampFormat,
+    Path(id): Path<String>,
+) -> Result<Json<AgentSummary>, ApiError> {
+    let agent = match state.agents.get(&id) {
+        Some(agent) => Some(summarize(&state, &agent, format)),
+        None => state.agents
+            .iter()
+            .find(|agent| agent.hostname == id)
+            .map(|agent| summarize(&state, &agent, format)),
+    };
+
+    agent.map(Json).ok_or(ApiError::UnknownAgent(id))
+}
+
+fn summarize(state: &AppState, agent: &AgentState, format: TimestampFormat) -> AgentSummary {
+    let published = |ip: &String| agent.ipv4.as_ref() == Some(ip) || agent.ipv6.contains(ip);
+    let addresses = agent.interfaces
+        .iter()
+        .flat_map(|interface| interface.ipv4.iter().chain(interface.ipv6.iter()).map(move |ip| (interface, ip)))
+        .map(|(interface, ip)| AgentAddress {
+            ip: ip.clone(),
+            interface: interface.name.clone(),
+            published: published(ip),
         })
         .collect();
 
-    Json(agents)
+    AgentSummary {
+        id: agent.id.clone(),
+        hostname: agent.hostname.clone(),
+        dns_name: state.naming.dns_name(&agent.hostname),
+        aliases: agent.aliases.clone(),
+        agent_version: agent.agent_version.clone(),
+        ipv4: agent.ipv4.clone(),
+        ipv6: agent.ipv6.clone(),
+        interfaces: agent.interfaces.clone(),
+        addresses,
+        online: state.liveness.is_online(agent),
+        registered_at: format.apply(agent.registered_at),
+        last_seen_at: format.apply(agent.last_seen_at),
+        last_seen_sec: agent.last_seen_elapsed().as_secs(),
+        last_ip_change_at: format.apply(agent.last_ip_change_at),
+        heartbeat_interval_sec: state.liveness.agent_interval(agent).as_secs(),
+        retired_at: agent.retired_at.map(|at| format.apply(at)),
+        stopped_at: agent.stopped_at.map(|at| format.apply(at)),
+    }
 }
 
-pub(crate) async fn stats(State(state): State<AppState>) -> Json<serde_json::Value> {
+#[utoipa::path(
+    get,
+    path = "/stats",
+    responses((status = 200, description = "Fleet counters", body = AgentStats)),
+)]
+pub(crate) async fn stats(State(state): State<AppState>) -> Json<AgentStats> {
     let total = state.agents.len();
     let online = state
         .agents
         .iter()
-        .filter(|a| a.last_seen.elapsed().as_secs() < 120)
+        .filter(|a| state.liveness.is_online(a))
         .count();
 
-    Json(serde_json::json!({
-        "agents_total": total,
-        "agents_online": online,
-        "agents_offline": total - online,
-    }))
-}
\ No newline at end of file
+    Json(AgentStats {
+        agents_total: total,
+        agents_online: online,
+        agents_offline: total - online,
+    })
+}
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, description = "Prometheus text exposition format", content_type = "text/plain")),
+)]
+pub(crate) async fn prometheus_metrics(State(state): State<AppState>) -> impl IntoResponse {
+    match state.metrics.render(&state.agents, &state.liveness) {
+        Ok(body) => (StatusCode::OK, [(CONTENT_TYPE, prometheus::TEXT_FORMAT)], body),
+        Err(e) => {
+            error!("Failed to render metrics: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, [(CONTENT_TYPE, "text/plain")], String::new())
+        }
+    }
+}
@@ -1,3 +1,10 @@
 pub mod agent;
 pub mod heart_beat;
-pub mod metric;
\ No newline at end of file
+pub mod metric;
+pub mod reconcile;
+pub mod notification;
+pub mod remove;
+pub mod deregister;
+pub mod events;
+pub mod stream;
+pub mod dashboard;
@@ -0,0 +1,17 @@
+use axum::{extract::State, Json};
+use core::dto::error_body::ErrorBody;
+use crate::model::state::AppState;
+use crate::notify::notifier::WebhookTestResult;
+
+#[utoipa::path(
+    post,
+    path = "/notifications/test",
+    responses(
+        (status = 200, description = "Outcome of sending a test event to each configured webhook", body = Vec<WebhookTestResult>),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorBody),
+    ),
+    security(("admin_token" = [])),
+)]
+pub(crate) async fn test_notifications(State(state): State<AppState>) -> Json<Vec<WebhookTestResult>> {
+    Json(state.notifier.test().await)
+}
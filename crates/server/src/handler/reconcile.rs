@@ -0,0 +1,42 @@
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use core::logging::error;
+use serde::Deserialize;
+use utoipa::IntoParams;
+use core::dto::error_body::ErrorBody;
+use crate::error::ApiError;
+use crate::model::state::AppState;
+use crate::reconcile::reconciler::{reconcile, ReconcileReport};
+
+#[derive(Deserialize, IntoParams)]
+pub(crate) struct ReconcileQuery {
+    /// Only report the changes without applying them
+    #[serde(default)]
+    dry_run: bool,
+}
+
+#[utoipa::path(
+    post,
+    path = "/reconcile",
+    params(ReconcileQuery),
+    responses(
+        (status = 200, description = "Changes applied, or planned in dry-run mode", body = ReconcileReport),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorBody),
+        (status = 502, description = "DNS backend failure", body = ErrorBody),
+    ),
+    security(("admin_token" = [])),
+)]
+pub(crate) async fn run_reconcile(
+    State(state): State<AppState>,
+    Query(query): Query<ReconcileQuery>,
+) -> Result<Json<ReconcileReport>, ApiError> {
+    match reconcile(&state, query.dry_run).await {
+        Ok(report) => Ok(Json(report)),
+        Err(e) => {
+            error!("DNS reconciliation failed: {}", e);
+            Err(ApiError::Dns(format!("Reconciliation failed: {}", e)))
+        }
+    }
+}
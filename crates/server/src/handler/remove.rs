@@ -0,0 +1,32 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+};
+use core::dto::error_body::ErrorBody;
+use core::logging::error;
+use crate::error::ApiError;
+use crate::liveness::reaper;
+use crate::model::state::AppState;
+
+#[utoipa::path(
+    delete,
+    path = "/agents/{id}",
+    params(("id" = String, Path, description = "Agent ID, the hostname for agents that predate IDs")),
+    responses(
+        (status = 204, description = "Agent forgotten and its DNS records removed"),
+        (status = 401, description = "Missing or invalid admin token", body = ErrorBody),
+        (status = 404, description = "Unknown agent", body = ErrorBody),
+        (status = 502, description = "DNS backend failure, the agent is kept", body = ErrorBody),
+    ),
+    security(("admin_token" = [])),
+)]
+pub(crate) async fn remove_agent(State(state): State<AppState>, Path(id): Path<String>) -> Result<StatusCode, ApiError> {
+    match reaper::remove(&state, &id).await {
+        Ok(true) => Ok(StatusCode::NO_CONTENT),
+        Ok(false) => Err(ApiError::UnknownAgent(id)),
+        Err(e) => {
+            error!("Failed to remove agent id={}: {}", id, e);
+            Err(ApiError::Dns(e))
+        }
+    }
+}
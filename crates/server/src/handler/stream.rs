@@ -0,0 +1,150 @@
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    response::{
+        sse::{Event as SseEvent, KeepAlive, Sse},
+        Response,
+    },
+};
+use core::logging::{debug, warn};
+use futures::{stream, Stream};
+use serde::Deserialize;
+use std::convert::Infallible;
+use tokio::sync::broadcast::{error::RecvError, Receiver};
+use utoipa::IntoParams;
+use crate::dto::timestamp::TimestampFormat;
+use crate::model::{event::RecordedEvent, state::AppState};
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct StreamQuery {
+    /// Only events about these agents, comma separated hostnames
+    hostname: Option<String>,
+    /// Only events of these types, comma separated, e.g. `ip_changed,agent_offline`
+    #[serde(rename = "type")]
+    #[param(rename = "type")]
+    kind: Option<String>,
+}
+
+/// Which live events a subscriber asked for.
+struct LiveFilter {
+    hostnames: Vec<String>,
+    kinds: Vec<String>,
+}
+
+impl LiveFilter {
+    fn new(query: StreamQuery) -> Self {
+        Self {
+            hostnames: split_list(query.hostname),
+            kinds: split_list(query.kind),
+        }
+    }
+
+    fn matches(&self, recorded: &RecordedEvent) -> bool {
+        let event = &recorded.event;
+        let hostname_matches = self.hostnames.is_empty()
+            || event.hostname().is_some_and(|hostname| self.hostnames.iter().any(|wanted| wanted == hostname));
+        let kind_matches = self.kinds.is_empty() || self.kinds.iter().any(|kind| kind == event.kind());
+
+        hostname_matches && kind_matches
+    }
+}
+
+fn split_list(list: Option<String>) -> Vec<String> {
+    list.iter()
+        .flat_map(|list| list.split(','))
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Waits for the next live event the filter lets through, `None` once the server shuts down.
+async fn next_event(live: &mut Receiver<RecordedEvent>, filter: &LiveFilter) -> Option<RecordedEvent> {
+    loop {
+        match live.recv().await {
+            Ok(recorded) if filter.matches(&recorded) => return Some(recorded),
+            Ok(_) => {}
+            Err(RecvError::Lagged(missed)) => warn!("Live event subscriber fell behind, skipped {} event(s)", missed),
+            Err(RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/events/stream",
+    params(StreamQuery, ("accept" = Option<String>, Header, description = "`application/vnd.piwatch.legacy+json` for timestamps in the pre-RFC 3339 shape")),
+    responses(
+        (status = 200, description = "Server-Sent Events stream of events as they happen, named after their type", content_type = "text/event-stream", body = RecordedEvent),
+    ),
+)]
+pub(crate) async fn stream_events(
+    State(state): State<AppState>,
+    format: TimestampFormat,
+    Query(query): Query<StreamQuery>,
+) -> Sse<impl Stream<Item = Result<SseEvent, Infallible>>> {
+    let subscription = (state.events.subscribe(), LiveFilter::new(query));
+    let events = stream::unfold(subscription, move |(mut live, filter)| async move {
+        let recorded = next_event(&mut live, &filter).await?.in_format(format);
+        Some((Ok(to_sse(&recorded)), (live, filter)))
+    });
+
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
+
+fn to_sse(recorded: &RecordedEvent) -> SseEvent {
+    let event = SseEvent::default().event(recorded.event.kind());
+    // Events only streamed live have no place in the history to resume from
+    let event = match recorded.seq {
+        0 => event,
+        seq => event.id(seq.to_string()),
+    };
+
+    event.json_data(recorded).unwrap_or_else(|_| SseEvent::default().comment("unserializable event"))
+}
+
+#[utoipa::path(
+    get,
+    path = "/events/ws",
+    params(StreamQuery, ("accept" = Option<String>, Header, description = "`application/vnd.piwatch.legacy+json` for timestamps in the pre-RFC 3339 shape")),
+    responses(
+        (status = 101, description = "WebSocket sending each event as a JSON text message as it happens", body = RecordedEvent),
+    ),
+)]
+pub(crate) async fn stream_events_ws(
+    State(state): State<AppState>,
+    format: TimestampFormat,
+    Query(query): Query<StreamQuery>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    let live = state.events.subscribe();
+    upgrade.on_upgrade(move |socket| forward(socket, live, LiveFilter::new(query), format))
+}
+
+async fn forward(mut socket: WebSocket, mut live: Receiver<RecordedEvent>, filter: LiveFilter, format: TimestampFormat) {
+    loop {
+        tokio::select! {
+            recorded = next_event(&mut live, &filter) => {
+                let Some(recorded) = recorded else {
+                    break;
+                };
+                let Ok(json) = serde_json::to_string(&recorded.in_format(format)) else {
+                    continue;
+                };
+                if socket.send(Message::Text(json.into())).await.is_err() {
+                    break;
+                }
+            }
+            // Clients only ever close the socket, pings are answered by axum
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    debug!("Live event WebSocket closed");
+}
@@ -0,0 +1,3 @@
+pub mod monitor;
+pub mod policy;
+pub mod reaper;
@@ -0,0 +1,53 @@
+use crate::liveness::reaper;
+use crate::model::{event::Event, state::AppState};
+use core::logging::warn;
+use std::time::Duration;
+
+/// Watches agent heartbeats and reports each agent going offline once, until it comes back.
+///
+/// Agents offline for longer than the retirement TTL lose their DNS records, and are
+/// forgotten once they have been retired for the purge TTL.
+pub(crate) fn spawn(state: AppState, interval: Duration) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            // Marked under the guard, recorded once it's released since recording writes to the store
+            let went_offline: Vec<(String, String, u64)> = state.agents
+                .iter_mut()
+                .filter(|agent| !agent.reported_offline && !state.liveness.is_online(agent))
+                .map(|mut agent| {
+                    agent.reported_offline = true;
+                    (agent.id.clone(), agent.hostname.clone(), agent.last_seen_elapsed().as_secs())
+                })
+                .collect();
+
+            for (id, hostname, last_seen_sec) in went_offline {
+                warn!("Agent {} id={} went offline, last seen {}s ago", hostname, id, last_seen_sec);
+                state.events.record(Event::AgentOffline { id, hostname, last_seen_sec });
+            }
+
+            // Collected first, retiring talks to the DNS backend and no map guard may be held across it
+            let to_retire: Vec<String> = state.agents
+                .iter()
+                .filter(|agent| state.liveness.should_retire(agent))
+                .map(|agent| agent.id.clone())
+                .collect();
+            let to_purge: Vec<String> = state.agents
+                .iter()
+                .filter(|agent| state.liveness.should_purge(agent))
+                .map(|agent| agent.id.clone())
+                .collect();
+
+            for id in to_retire {
+                if let Err(e) = reaper::retire(&state, &id).await {
+                    warn!("Failed to retire agent id={}, will retry: {}", id, e);
+                }
+            }
+
+            for id in to_purge {
+                reaper::purge(&state, &id);
+            }
+        }
+    });
+}
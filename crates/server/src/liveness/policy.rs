@@ -0,0 +1,69 @@
+use crate::model::state::AgentState;
+use std::time::{Duration, SystemTime};
+
+/// Agents may not heartbeat more often than this, whatever they ask for.
+const MIN_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Decides how often agents send heartbeats and when an agent counts as offline.
+pub(crate) struct LivenessPolicy {
+    default_interval: Duration,
+    max_interval: Duration,
+    miss_factor: u32,
+    retire_after: Option<Duration>,
+    purge_after: Option<Duration>,
+}
+
+impl LivenessPolicy {
+    /// `retire_after_hours` and `purge_after_hours` disable retiring and purging agents when 0.
+    pub(crate) fn new(
+        default_interval_secs: u64,
+        max_interval_secs: u64,
+        miss_factor: u32,
+        retire_after_hours: u64,
+        purge_after_hours: u64,
+    ) -> Self {
+        let hours = |hours: u64| (hours > 0).then(|| Duration::from_secs(hours * 3600));
+
+        Self {
+            default_interval: Duration::from_secs(default_interval_secs),
+            max_interval: Duration::from_secs(max_interval_secs),
+            miss_factor,
+            retire_after: hours(retire_after_hours),
+            purge_after: hours(purge_after_hours),
+        }
+    }
+
+    /// The heartbeat interval an agent asking for `requested_secs` gets, within the configured bounds.
+    pub(crate) fn heartbeat_interval(&self, requested_secs: Option<u64>) -> Duration {
+        match requested_secs {
+            Some(secs) => Duration::from_secs(secs).clamp(MIN_HEARTBEAT_INTERVAL.min(self.max_interval), self.max_interval),
+            None => self.default_interval,
+        }
+    }
+
+    /// The interval `agent` heartbeats at, the default for agents registered before it was negotiated.
+    pub(crate) fn agent_interval(&self, agent: &AgentState) -> Duration {
+        agent.heartbeat_interval.unwrap_or(self.default_interval)
+    }
+
+    /// How long the agent may stay silent before it is offline, `miss_factor` heartbeats.
+    pub(crate) fn offline_after(&self, agent: &AgentState) -> Duration {
+        self.agent_interval(agent) * self.miss_factor
+    }
+
+    /// Agents that announced they were stopping are offline right away.
+    pub(crate) fn is_online(&self, agent: &AgentState) -> bool {
+        agent.stopped_at.is_none() && agent.last_seen_elapsed() < self.offline_after(agent)
+    }
+
+    /// Whether `agent` has been silent long enough to withdraw its DNS records.
+    pub(crate) fn should_retire(&self, agent: &AgentState) -> bool {
+        agent.retired_at.is_none() && self.retire_after.is_some_and(|after| agent.last_seen_elapsed() >= after)
+    }
+
+    /// Whether `agent` has been retired long enough to be forgotten.
+    pub(crate) fn should_purge(&self, agent: &AgentState) -> bool {
+        let retired_for = agent.retired_at.and_then(|at| SystemTime::now().duration_since(at).ok());
+        matches!((retired_for, self.purge_after), (Some(retired_for), Some(after)) if retired_for >= after)
+    }
+}
@@ -0,0 +1,120 @@
+use crate::model::{event::Event, state::AppState};
+use core::logging::{error, info, warn};
+use std::time::SystemTime;
+
+/// Withdraws the DNS records of an agent that stayed offline too long and marks it retired.
+///
+/// The agent is marked first so a late heartbeat gets told to register again, which
+/// publishes its records anew. If the records can't be removed the agent is left as it was.
+pub(crate) async fn retire(state: &AppState, id: &str) -> Result<(), String> {
+    let retired_at = SystemTime::now();
+    let (hostname, ips, aliases, last_seen_sec) = {
+        let Some(mut agent) = state.agents.get_mut(id) else {
+            return Ok(());
+        };
+        if !state.liveness.should_retire(&agent) {
+            return Ok(());
+        }
+
+        agent.retired_at = Some(retired_at);
+        (
+            agent.hostname.clone(),
+            agent.ipv4.iter().chain(agent.ipv6.iter()).cloned().collect::<Vec<_>>(),
+            agent.aliases.clone(),
+            agent.last_seen_elapsed().as_secs(),
+        )
+    };
+
+    if let Err(e) = withdraw_dns(state, &hostname, ips, &aliases).await {
+        // Unless the agent registered again in the meantime
+        if let Some(mut agent) = state.agents.get_mut(id).filter(|agent| agent.retired_at == Some(retired_at)) {
+            agent.retired_at = None;
+        }
+        return Err(e);
+    }
+
+    let retired = state.agents.get(id).map(|agent| agent.clone());
+    if let Some(Err(e)) = retired.map(|agent| state.store.save_agent(&agent)) {
+        error!("Failed to persist retired agent hostname={}: {}", hostname, e);
+    }
+
+    info!("Retired agent {} id={} after {}s offline", hostname, id, last_seen_sec);
+    state.events.record(Event::AgentRetired { id: id.to_string(), hostname, last_seen_sec });
+
+    Ok(())
+}
+
+/// Forgets a retired agent, its DNS records are already gone.
+pub(crate) fn purge(state: &AppState, id: &str) {
+    let Some((_, agent)) = state.agents.remove_if(id, |_, agent| state.liveness.should_purge(agent)) else {
+        return;
+    };
+
+    if let Err(e) = state.store.delete_agent(id) {
+        error!("Failed to delete purged agent hostname={}: {}", agent.hostname, e);
+    }
+
+    info!("Purged retired agent {} id={}", agent.hostname, id);
+    state.events.record(Event::AgentRemoved { id: agent.id, hostname: agent.hostname, by_admin: false });
+}
+
+/// Removes an agent and its DNS records right away, returning `false` if the agent is unknown.
+///
+/// An agent that is still running gets told to register again on its next heartbeat.
+pub(crate) async fn remove(state: &AppState, id: &str) -> Result<bool, String> {
+    let Some((hostname, ips, aliases)) = state.agents.get(id).map(|agent| (
+        agent.hostname.clone(),
+        agent.ipv4.iter().chain(agent.ipv6.iter()).cloned().collect::<Vec<_>>(),
+        agent.aliases.clone(),
+    )) else {
+        return Ok(false);
+    };
+
+    withdraw_dns(state, &hostname, ips, &aliases).await?;
+
+    state.agents.remove(id);
+    if let Err(e) = state.store.delete_agent(id) {
+        error!("Failed to delete agent hostname={}: {}", hostname, e);
+    }
+
+    info!("Removed agent {} id={}", hostname, id);
+    state.events.record(Event::AgentRemoved { id: id.to_string(), hostname, by_admin: true });
+
+    Ok(true)
+}
+
+/// Removes the host records and aliases published for `hostname`, including addresses only the ledger remembers.
+pub(crate) async fn withdraw_dns(state: &AppState, hostname: &str, mut ips: Vec<String>, aliases: &[String]) -> Result<(), String> {
+    match state.store.load_dns_records() {
+        Ok(records) => {
+            for record in records.into_iter().filter(|record| record.hostname == hostname) {
+                if !ips.contains(&record.ip) {
+                    ips.push(record.ip);
+                }
+            }
+        }
+        Err(e) => error!("Failed to load DNS records of hostname={}: {}", hostname, e),
+    }
+
+    if let Some(dns_name) = state.naming.dns_name(hostname) {
+        for ip in &ips {
+            if let Err(e) = state.dns.remove_host(&dns_name, ip).await.map_err(|e| e.to_string()) {
+                state.events.dns_sync(state.dns.name(), Err(e.clone()));
+                return Err(format!("Failed to remove IP {} of {}: {}", ip, hostname, e));
+            }
+        }
+
+        for alias in aliases {
+            if let Err(e) = state.dns.remove_cname(alias, &dns_name).await {
+                warn!("Failed to remove alias {} of hostname={}: {}", alias, hostname, e);
+            }
+        }
+        state.events.dns_sync(state.dns.name(), Ok(()));
+    }
+
+    if let Err(e) = state.store.set_dns_records(hostname, &[]) {
+        error!("Failed to forget DNS records for hostname={}: {}", hostname, e);
+    }
+
+    Ok(())
+}
@@ -2,18 +2,42 @@ mod model;
 mod handler;
 mod dto;
 mod pihole;
+mod reconcile;
 mod config;
+mod metrics;
+mod openapi;
+mod dns;
+mod error;
+mod middleware;
+mod storage;
+mod notify;
+mod liveness;
+mod audit;
 
-use axum::{routing::{get, post},Router};
+use axum::{middleware::from_fn_with_state, routing::{delete, get, post},Router};
 use dashmap::DashMap;
-use std::{sync::Arc,time::{Duration},};
+use std::{net::SocketAddr, sync::Arc,time::{Duration},};
 use core::logging::{info, warn};
 use crate::{
-    config::load_config, handler::{
+    audit::event_log::{spawn_retention, EventLog},
+    config::{load_config, DnsBackendKind}, dns::{backend::DnsBackend, hosts_file::HostsFileBackend, naming::NamingPolicy}, handler::{
         agent::{register, update_ip},
+        dashboard,
+        deregister::deregister,
+        events::{agent_events, list_events},
         heart_beat::heartbeat,
-        metric::{list_agents, stats},
-    }, model::state::AppState
+        metric::{get_agent, list_agents, prometheus_metrics, stats},
+        notification::test_notifications,
+        remove::remove_agent,
+        reconcile::run_reconcile,
+        stream::{stream_events, stream_events_ws},
+    },
+    liveness::policy::LivenessPolicy,
+    metrics::{instrumented_dns::InstrumentedDnsBackend, server_metrics::ServerMetrics},
+    middleware::{auth::{require_admin, verify_signature}, metrics::track_requests, replay_guard::ReplayGuard}, model::state::AppState,
+    notify::notifier::Notifier,
+    openapi::openapi_json,
+    storage::{agent_store::AgentStore, sqlite::SqliteAgentStore},
 };
 use pihole::client::PiholeClient;
 
@@ -23,47 +47,109 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Ok(cfg) => cfg,
         Err(e) => {
             eprintln!("Failed to load configuration: {}", e);
-            return Err(e.into());
+            return Err(e);
         }
     };
 
     core::logging::init(&config.log_level);
 
-    let http_client = reqwest::Client::new();
+    let metrics = Arc::new(ServerMetrics::new()?);
 
+    let dns: Arc<dyn DnsBackend> = match config.dns_backend {
+        DnsBackendKind::Pihole => Arc::new(PiholeClient::new(reqwest::Client::new(), &config.pihole_url, &config.pihole_pass)),
+        DnsBackendKind::HostsFile => Arc::new(HostsFileBackend::new(&config.hosts_file_path)),
+    };
+    let dns: Arc<dyn DnsBackend> = Arc::new(InstrumentedDnsBackend::new(dns, metrics.clone()));
+
+    if let Err(e) = dns.health().await {
+        warn!("DNS backend {} is not healthy yet: {}", dns.name(), e);
+    }
+
+    let store = Arc::new(SqliteAgentStore::open(&config.database_path)?);
+    let agents = DashMap::new();
+    let liveness = LivenessPolicy::new(
+        config.heartbeat_interval_secs,
+        config.max_heartbeat_interval_secs,
+        config.heartbeat_miss_factor,
+        config.retire_after_hours,
+        config.purge_after_hours,
+    );
+    for mut agent in store.load_agents()? {
+        // Agents already offline before a restart were reported by the previous run
+        agent.reported_offline = !liveness.is_online(&agent);
+        agents.insert(agent.id.clone(), agent);
+    }
+    info!("Loaded {} agent(s) from {}", agents.len(), &config.database_path);
+
+    let notifier = Arc::new(Notifier::start(reqwest::Client::new(), config.webhooks.clone()));
     let state = AppState {
-        agents: Arc::new(DashMap::new()),
-        pihole_client: Arc::new(PiholeClient::new(http_client, &config.pihole_url, &config.pihole_pass)),
+        agents: Arc::new(agents),
+        registrations: Arc::new(tokio::sync::Mutex::new(())),
+        dns,
+        naming: Arc::new(NamingPolicy::new(config.dns_domain.as_deref())),
+        liveness: Arc::new(liveness),
+        store: store.clone(),
+        agent_secret: Arc::new(config.agent_secret.clone()),
+        replay_guard: Arc::new(ReplayGuard::new()),
+        admin_token: config.admin_token.clone().map(Arc::new),
+        metrics,
+        notifier: notifier.clone(),
+        events: Arc::new(EventLog::new(store.clone(), notifier)),
     };
 
-    // Background cleanup / logging task
-    {
-        let agents = state.agents.clone();
-        tokio::spawn(async move {
-            loop {
-                for entry in agents.iter() {
-                    if entry.last_seen.elapsed() > Duration::from_secs(300) {
-                        warn!("Node {} has been offline for >5m", entry.key());
-                    }
-                }
-                tokio::time::sleep(Duration::from_secs(60)).await;
-            }
-        });
+    if config.event_retention_days > 0 {
+        spawn_retention(store, Duration::from_secs(config.event_retention_days * 86400));
     }
 
-    // TODO: API versioning
-    let app = Router::new()
+    if config.reconcile_interval_secs > 0 {
+        reconcile::reconciler::spawn(
+            state.clone(),
+            Duration::from_secs(config.reconcile_interval_secs),
+            config.reconcile_dry_run,
+        );
+    }
+
+    liveness::monitor::spawn(state.clone(), Duration::from_secs(config.liveness_check_interval_secs));
+
+    let agent_routes = Router::new()
         .route("/register", post(register))
         .route("/update", post(update_ip))
         .route("/heartbeat", post(heartbeat))
+        .route("/deregister", post(deregister))
+        .route_layer(from_fn_with_state(state.clone(), verify_signature))
+        .route_layer(from_fn_with_state(state.clone(), track_requests));
+
+    let admin_routes = Router::new()
+        .route("/reconcile", post(run_reconcile))
+        .route("/notifications/test", post(test_notifications))
+        .route("/agents/{id}", delete(remove_agent))
+        .route_layer(from_fn_with_state(state.clone(), require_admin));
+
+    let api_routes = Router::new()
+        .merge(agent_routes)
+        .merge(admin_routes)
         .route("/agents", get(list_agents))
+        .route("/agents/{id}", get(get_agent))
+        .route("/agents/{id}/events", get(agent_events))
+        .route("/events", get(list_events))
+        .route("/events/stream", get(stream_events))
+        .route("/events/ws", get(stream_events_ws))
         .route("/stats", get(stats))
+        .route("/metrics", get(prometheus_metrics));
+
+    let app = Router::new()
+        .nest("/api/v1", api_routes.clone().route("/openapi.json", get(openapi_json)))
+        // Unversioned routes kept for signing agents still configured with the pre-/api/v1 URLs
+        .merge(api_routes)
+        .route("/", get(dashboard::index))
+        .route("/dashboard/app.js", get(dashboard::app_js))
+        .route("/dashboard/style.css", get(dashboard::style_css))
         .with_state(state);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:".to_owned() + &config.bind_port.to_string()).await.unwrap();
-    info!("PiWatch server listening on http://localhost:{}", &config.bind_port);
+    info!("PiWatch server listening on http://localhost:{}, dashboard at /", &config.bind_port);
 
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 
     Ok(())
 }
@@ -0,0 +1,61 @@
+use crate::dns::backend::{DnsBackend, DnsFuture, DnsResult};
+use crate::metrics::server_metrics::ServerMetrics;
+use crate::model::dns_record::DnsRecord;
+use std::{future::Future, sync::Arc, time::Instant};
+
+/// Wraps a `DnsBackend` to record the latency of every call.
+pub(crate) struct InstrumentedDnsBackend {
+    inner: Arc<dyn DnsBackend>,
+    metrics: Arc<ServerMetrics>,
+}
+
+impl InstrumentedDnsBackend {
+    pub(crate) fn new(inner: Arc<dyn DnsBackend>, metrics: Arc<ServerMetrics>) -> Self {
+        Self { inner, metrics }
+    }
+
+    fn observe<'a, T: 'a>(&'a self, operation: &'static str, call: impl Future<Output = DnsResult<T>> + Send + 'a) -> DnsFuture<'a, T> {
+        Box::pin(async move {
+            let started = Instant::now();
+            let result = call.await;
+            let outcome = if result.is_ok() { "ok" } else { "error" };
+
+            self.metrics
+                .dns_request_duration
+                .with_label_values(&[self.inner.name(), operation, outcome])
+                .observe(started.elapsed().as_secs_f64());
+
+            result
+        })
+    }
+}
+
+impl DnsBackend for InstrumentedDnsBackend {
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn upsert_host<'a>(&'a self, hostname: &'a str, ip: &'a str) -> DnsFuture<'a, ()> {
+        self.observe("upsert_host", self.inner.upsert_host(hostname, ip))
+    }
+
+    fn remove_host<'a>(&'a self, hostname: &'a str, ip: &'a str) -> DnsFuture<'a, ()> {
+        self.observe("remove_host", self.inner.remove_host(hostname, ip))
+    }
+
+    fn list_hosts(&self) -> DnsFuture<'_, Vec<DnsRecord>> {
+        self.observe("list_hosts", self.inner.list_hosts())
+    }
+
+    fn upsert_cname<'a>(&'a self, alias: &'a str, target: &'a str) -> DnsFuture<'a, ()> {
+        self.observe("upsert_cname", self.inner.upsert_cname(alias, target))
+    }
+
+    fn remove_cname<'a>(&'a self, alias: &'a str, target: &'a str) -> DnsFuture<'a, ()> {
+        self.observe("remove_cname", self.inner.remove_cname(alias, target))
+    }
+
+    fn health(&self) -> DnsFuture<'_, ()> {
+        self.observe("health", self.inner.health())
+    }
+}
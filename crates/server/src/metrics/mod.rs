@@ -0,0 +1,2 @@
+pub mod instrumented_dns;
+pub mod server_metrics;
@@ -0,0 +1,72 @@
+use crate::liveness::policy::LivenessPolicy;
+use crate::model::state::Agents;
+use prometheus::{
+    Encoder, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder,
+};
+
+pub(crate) struct ServerMetrics {
+    registry: Registry,
+    pub requests: IntCounterVec,
+    pub dns_request_duration: HistogramVec,
+    agent_up: GaugeVec,
+    agent_last_seen: GaugeVec,
+}
+
+impl ServerMetrics {
+    pub(crate) fn new() -> Result<Self, prometheus::Error> {
+        let registry = Registry::new_custom(Some("piwatch".to_string()), None)?;
+
+        let requests = IntCounterVec::new(
+            Opts::new("agent_requests_total", "Agent requests by endpoint and outcome"),
+            &["endpoint", "outcome"],
+        )?;
+        let dns_request_duration = HistogramVec::new(
+            HistogramOpts::new("dns_backend_request_duration_seconds", "Latency of DNS backend calls")
+                .buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["backend", "operation", "outcome"],
+        )?;
+        let agent_up = GaugeVec::new(
+            Opts::new("agent_up", "Whether the agent sent a heartbeat recently"),
+            &["hostname"],
+        )?;
+        let agent_last_seen = GaugeVec::new(
+            Opts::new("agent_last_seen_seconds", "Seconds since the agent was last seen"),
+            &["hostname"],
+        )?;
+
+        registry.register(Box::new(requests.clone()))?;
+        registry.register(Box::new(dns_request_duration.clone()))?;
+        registry.register(Box::new(agent_up.clone()))?;
+        registry.register(Box::new(agent_last_seen.clone()))?;
+
+        Ok(Self {
+            registry,
+            requests,
+            dns_request_duration,
+            agent_up,
+            agent_last_seen,
+        })
+    }
+
+    /// Renders every metric in the Prometheus text format, refreshing per-agent gauges first.
+    pub(crate) fn render(&self, agents: &Agents, liveness: &LivenessPolicy) -> Result<String, prometheus::Error> {
+        // Reset so agents that disappeared from the registry stop being exported
+        self.agent_up.reset();
+        self.agent_last_seen.reset();
+
+        for agent in agents.iter() {
+            let last_seen = agent.last_seen_elapsed().as_secs();
+            self.agent_up
+                .with_label_values(&[agent.hostname.as_str()])
+                .set(if liveness.is_online(&agent) { 1.0 } else { 0.0 });
+            self.agent_last_seen
+                .with_label_values(&[agent.hostname.as_str()])
+                .set(last_seen as f64);
+        }
+
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+
+        Ok(String::from_utf8_lossy(&buffer).into_owned())
+    }
+}
@@ -0,0 +1,102 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{ConnectInfo, Request, State},
+    http::{header::AUTHORIZATION, HeaderMap},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use core::auth::bearer;
+use core::auth::signature::{verify, MAX_TIMESTAMP_SKEW_SECS, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use core::logging::warn;
+use std::{
+    net::SocketAddr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use crate::error::ApiError;
+use crate::model::state::AppState;
+
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+/// Rejects agent requests that are not signed with the shared agent secret, or replay one that was.
+pub(crate) async fn verify_signature(
+    State(state): State<AppState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+
+    let body = match to_bytes(body, MAX_BODY_SIZE).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            warn!("Rejected {} {} from {}: body too large", parts.method, parts.uri, remote);
+            return ApiError::PayloadTooLarge.into_response();
+        }
+    };
+
+    // Inside the /api/v1 nest the path is relative to it, the same path the agent signs
+    let checked = check_signature(&state.agent_secret, parts.method.as_str(), parts.uri.path(), &parts.headers, &body)
+        .and_then(|(timestamp, signature, now)| match state.replay_guard.first_use(signature, timestamp, now) {
+            true => Ok(()),
+            false => Err("signature already used"),
+        });
+    if let Err(reason) = checked {
+        warn!("Rejected {} {} from {}: {}", parts.method, parts.uri, remote, reason);
+        return ApiError::Unauthorized(reason.to_string()).into_response();
+    }
+
+    next.run(Request::from_parts(parts, Body::from(body))).await
+}
+
+/// Returns the verified timestamp and signature along with the current time.
+fn check_signature<'a>(
+    secret: &str,
+    method: &str,
+    path: &str,
+    headers: &'a HeaderMap,
+    body: &[u8],
+) -> Result<(u64, &'a str, u64), &'static str> {
+    let timestamp = headers
+        .get(TIMESTAMP_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+        .ok_or("missing or invalid timestamp")?;
+
+    let signature = headers
+        .get(SIGNATURE_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .ok_or("missing signature")?;
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    if now.abs_diff(timestamp) > MAX_TIMESTAMP_SKEW_SECS {
+        return Err("timestamp outside of allowed window");
+    }
+
+    if !verify(secret, method, path, timestamp, body, signature) {
+        return Err("invalid signature");
+    }
+
+    Ok((timestamp, signature, now))
+}
+
+/// Guards admin endpoints with the `Authorization: Bearer <admin_token>` header.
+pub(crate) async fn require_admin(
+    State(state): State<AppState>,
+    ConnectInfo(remote): ConnectInfo<SocketAddr>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(admin_token) = state.admin_token.as_deref() else {
+        warn!("Rejected {} {} from {}: admin API disabled", request.method(), request.uri(), remote);
+        return ApiError::Unauthorized("Admin API is disabled, set ADMIN_TOKEN to enable it".to_string()).into_response();
+    };
+
+    let authorization = request.headers().get(AUTHORIZATION).and_then(|v| v.to_str().ok());
+
+    if !bearer::matches(authorization, admin_token) {
+        warn!("Rejected {} {} from {}: invalid admin token", request.method(), request.uri(), remote);
+        return ApiError::Unauthorized("Invalid admin token".to_string()).into_response();
+    }
+
+    next.run(request).await
+}
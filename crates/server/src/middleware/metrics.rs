@@ -0,0 +1,40 @@
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use crate::model::state::AppState;
+
+/// Counts agent requests by endpoint and outcome, including those rejected by authentication.
+pub(crate) async fn track_requests(
+    State(state): State<AppState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let endpoint = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().rsplit('/').next().unwrap_or_default().to_string())
+        .unwrap_or_default();
+
+    let response = next.run(request).await;
+
+    state
+        .metrics
+        .requests
+        .with_label_values(&[endpoint.as_str(), outcome(response.status())])
+        .inc();
+
+    response
+}
+
+fn outcome(status: StatusCode) -> &'static str {
+    match status {
+        s if s.is_success() => "ok",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::NOT_FOUND => "unknown_agent",
+        s if s.is_client_error() => "invalid",
+        _ => "error",
+    }
+}
@@ -0,0 +1,3 @@
+pub mod auth;
+pub mod metrics;
+pub mod replay_guard;
@@ -0,0 +1,50 @@
+use core::auth::signature::MAX_TIMESTAMP_SKEW_SECS;
+use std::{collections::HashMap, sync::Mutex};
+
+/// Remembers the signatures accepted within the timestamp window, so a captured agent
+/// request can't be sent again while its timestamp is still accepted.
+pub(crate) struct ReplayGuard {
+    seen: Mutex<HashMap<String, u64>>,
+}
+
+impl ReplayGuard {
+    pub(crate) fn new() -> Self {
+        Self {
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records a valid signature, returning false if it was already used.
+    pub(crate) fn first_use(&self, signature: &str, timestamp: u64, now: u64) -> bool {
+        let mut seen = self.seen.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        // Anything older fails the timestamp check before it gets here
+        seen.retain(|_, signed_at| now.abs_diff(*signed_at) <= MAX_TIMESTAMP_SKEW_SECS);
+
+        // Hex is case-insensitive, the same MAC must not pass twice under another spelling
+        seen.insert(signature.to_ascii_lowercase(), timestamp).is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_each_signature_once() {
+        let guard = ReplayGuard::new();
+
+        assert!(guard.first_use("ab12", 1000, 1000));
+        assert!(!guard.first_use("ab12", 1000, 1001));
+        assert!(!guard.first_use("AB12", 1000, 1001));
+        assert!(guard.first_use("cd34", 1000, 1001));
+    }
+
+    #[test]
+    fn forgets_signatures_outside_the_window() {
+        let guard = ReplayGuard::new();
+        guard.first_use("ab12", 1000, 1000);
+
+        guard.first_use("cd34", 2000, 1000 + MAX_TIMESTAMP_SKEW_SECS + 1);
+        assert_eq!(guard.seen.lock().unwrap().len(), 1);
+    }
+}
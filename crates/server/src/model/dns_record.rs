@@ -0,0 +1,9 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// A single `ip hostname` line in the local DNS hosts list.
+#[derive(Serialize, ToSchema, Clone, PartialEq, Eq, Hash, Debug)]
+pub(crate) struct DnsRecord {
+    pub hostname: String,
+    pub ip: String,
+}
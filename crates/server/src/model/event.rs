@@ -0,0 +1,176 @@
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use crate::dto::timestamp::{Timestamp, TimestampFormat};
+
+/// Something that happened to an agent, the DNS backend or through the admin API.
+///
+/// Every event is kept in the history, the notable ones are also sent to webhooks.
+#[derive(Serialize, Deserialize, ToSchema, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum Event {
+    /// Sent on every registration, `first` for agents the server didn't know yet.
+    AgentRegistered { id: String, hostname: String, addresses: Vec<String>, first: bool },
+    AgentOffline { id: String, hostname: String, last_seen_sec: u64 },
+    AgentOnline { id: String, hostname: String },
+    AgentStopped { id: String, hostname: String, dns_kept: bool },
+    /// Only streamed live, far too frequent for the history.
+    Heartbeat { id: String, hostname: String },
+    /// An address added to or removed from an interface, as reported by the agent.
+    IpReported { id: String, hostname: String, interface: String, event: String, ip: String },
+    /// The set of addresses published in DNS for the agent changed.
+    IpChanged { id: String, hostname: String, old: Vec<String>, new: Vec<String> },
+    AgentRetired { id: String, hostname: String, last_seen_sec: u64 },
+    /// `by_admin` when removed through the admin API, otherwise purged after retirement.
+    AgentRemoved { id: String, hostname: String, by_admin: bool },
+    DnsSyncFailed { backend: String, error: String },
+    DnsSyncRecovered { backend: String },
+    Reconciled { dry_run: bool, added: usize, removed: usize, failed: usize },
+    Test,
+}
+
+impl Event {
+    pub(crate) fn kind(&self) -> &'static str {
+        match self {
+            Event::AgentRegistered { .. } => "agent_registered",
+            Event::AgentOffline { .. } => "agent_offline",
+            Event::AgentOnline { .. } => "agent_online",
+            Event::AgentStopped { .. } => "agent_stopped",
+            Event::Heartbeat { .. } => "heartbeat",
+            Event::IpReported { .. } => "ip_reported",
+            Event::IpChanged { .. } => "ip_changed",
+            Event::AgentRetired { .. } => "agent_retired",
+            Event::AgentRemoved { .. } => "agent_removed",
+            Event::DnsSyncFailed { .. } => "dns_sync_failed",
+            Event::DnsSyncRecovered { .. } => "dns_sync_recovered",
+            Event::Reconciled { .. } => "reconciled",
+            Event::Test => "test",
+        }
+    }
+
+    /// The agent the event is about, if any.
+    pub(crate) fn agent_id(&self) -> Option<&str> {
+        match self {
+            Event::AgentRegistered { id, .. }
+            | Event::AgentOffline { id, .. }
+            | Event::AgentOnline { id, .. }
+            | Event::AgentStopped { id, .. }
+            | Event::Heartbeat { id, .. }
+            | Event::IpReported { id, .. }
+            | Event::IpChanged { id, .. }
+            | Event::AgentRetired { id, .. }
+            | Event::AgentRemoved { id, .. } => Some(id),
+            Event::DnsSyncFailed { .. } | Event::DnsSyncRecovered { .. } | Event::Reconciled { .. } | Event::Test => None,
+        }
+    }
+
+    /// Hostname of the agent the event is about, if any.
+    pub(crate) fn hostname(&self) -> Option<&str> {
+        match self {
+            Event::AgentRegistered { hostname, .. }
+            | Event::AgentOffline { hostname, .. }
+            | Event::AgentOnline { hostname, .. }
+            | Event::AgentStopped { hostname, .. }
+            | Event::Heartbeat { hostname, .. }
+            | Event::IpReported { hostname, .. }
+            | Event::IpChanged { hostname, .. }
+            | Event::AgentRetired { hostname, .. }
+            | Event::AgentRemoved { hostname, .. } => Some(hostname),
+            Event::DnsSyncFailed { .. } | Event::DnsSyncRecovered { .. } | Event::Reconciled { .. } | Event::Test => None,
+        }
+    }
+
+    /// Whether webhooks hear about the event, routine registrations and raw address reports only go to the history.
+    pub(crate) fn is_notable(&self) -> bool {
+        match self {
+            Event::AgentRegistered { first, .. } => *first,
+            Event::Heartbeat { .. } | Event::IpReported { .. } | Event::Reconciled { .. } => false,
+            _ => true,
+        }
+    }
+
+    pub(crate) fn title(&self) -> String {
+        match self {
+            Event::AgentRegistered { hostname, .. } => format!("{} registered", hostname),
+            Event::AgentOffline { hostname, .. } => format!("{} is offline", hostname),
+            Event::AgentOnline { hostname, .. } => format!("{} is back online", hostname),
+            Event::AgentStopped { hostname, .. } => format!("{} stopped", hostname),
+            Event::Heartbeat { hostname, .. } => format!("{} sent a heartbeat", hostname),
+            Event::IpReported { hostname, .. } => format!("{} reported an address change", hostname),
+            Event::IpChanged { hostname, .. } => format!("{} changed address", hostname),
+            Event::AgentRetired { hostname, .. } => format!("{} retired", hostname),
+            Event::AgentRemoved { hostname, .. } => format!("{} removed", hostname),
+            Event::DnsSyncFailed { backend, .. } => format!("{} sync failed", backend),
+            Event::DnsSyncRecovered { backend } => format!("{} sync recovered", backend),
+            Event::Reconciled { .. } => "DNS reconciled".to_string(),
+            Event::Test => "Test notification".to_string(),
+        }
+    }
+
+    pub(crate) fn message(&self) -> String {
+        match self {
+            Event::AgentRegistered { id, hostname, addresses, first: true } => {
+                format!("New agent {} ({}) registered with {}", hostname, id, join_or_none(addresses))
+            }
+            Event::AgentRegistered { hostname, addresses, first: false, .. } => {
+                format!("{} registered again with {}", hostname, join_or_none(addresses))
+            }
+            Event::AgentOffline { hostname, last_seen_sec, .. } => {
+                format!("No heartbeat from {} for {}s", hostname, last_seen_sec)
+            }
+            Event::AgentOnline { hostname, .. } => format!("{} is sending heartbeats again", hostname),
+            Event::AgentStopped { hostname, dns_kept: true, .. } => format!("{} shut down on purpose, its DNS records were kept", hostname),
+            Event::AgentStopped { hostname, dns_kept: false, .. } => format!("{} shut down on purpose, its DNS records were removed", hostname),
+            Event::Heartbeat { hostname, .. } => format!("{} is alive", hostname),
+            Event::IpReported { hostname, interface, event, ip, .. } => {
+                format!("{} reported {} {} on interface {}", hostname, event, ip, interface)
+            }
+            Event::IpChanged { hostname, old, new, .. } => {
+                format!("{} moved from {} to {}", hostname, join_or_none(old), join_or_none(new))
+            }
+            Event::AgentRetired { hostname, last_seen_sec, .. } => {
+                format!("Removed the DNS records of {} after {}h offline", hostname, last_seen_sec / 3600)
+            }
+            Event::AgentRemoved { hostname, by_admin: true, .. } => format!("{} and its DNS records were removed by an admin", hostname),
+            Event::AgentRemoved { hostname, by_admin: false, .. } => format!("Forgot {} after it stayed retired", hostname),
+            Event::DnsSyncFailed { backend, error } => format!("Publishing records to {} failed: {}", backend, error),
+            Event::DnsSyncRecovered { backend } => format!("Publishing records to {} works again", backend),
+            Event::Reconciled { dry_run: true, added, removed, .. } => {
+                format!("Reconciliation would add {} and remove {} record(s)", added, removed)
+            }
+            Event::Reconciled { dry_run: false, added, removed, failed } => {
+                format!("Reconciliation added {} and removed {} record(s), {} failed", added, removed, failed)
+            }
+            Event::Test => "PiWatch notifications are working".to_string(),
+        }
+    }
+
+    /// Whether the event reports something broken rather than good news.
+    pub(crate) fn is_alert(&self) -> bool {
+        matches!(self, Event::AgentOffline { .. } | Event::DnsSyncFailed { .. })
+    }
+}
+
+fn join_or_none(addresses: &[String]) -> String {
+    match addresses.is_empty() {
+        true => "no address".to_string(),
+        false => addresses.join(", "),
+    }
+}
+
+/// An event as kept in the history.
+#[derive(Serialize, ToSchema, Clone)]
+pub(crate) struct RecordedEvent {
+    /// Increasing sequence number, usable as a pagination cursor, 0 for events only streamed live
+    pub seq: i64,
+    #[schema(value_type = String, format = DateTime)]
+    pub at: Timestamp,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+impl RecordedEvent {
+    pub(crate) fn in_format(mut self, format: TimestampFormat) -> Self {
+        self.at = self.at.in_format(format);
+        self
+    }
+}
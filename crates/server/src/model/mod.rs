@@ -1 +1,4 @@
-pub mod state;
\ No newline at end of file
+pub mod state;
+pub mod dns_record;
+pub mod event;
+pub mod subnet;
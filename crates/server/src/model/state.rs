@@ -1,6 +1,13 @@
-use crate::pihole::client::PiholeClient;
+use crate::audit::event_log::EventLog;
+use crate::dns::{backend::DnsBackend, naming::NamingPolicy};
+use crate::liveness::policy::LivenessPolicy;
+use crate::metrics::server_metrics::ServerMetrics;
+use crate::middleware::replay_guard::ReplayGuard;
+use crate::notify::notifier::Notifier;
+use crate::storage::agent_store::AgentStore;
+use core::dto::network_interface::NetworkInterface;
 use std::{
-    time::{Instant, SystemTime}
+    time::{Duration, SystemTime}
 };
 use dashmap::DashMap;
 use std::sync::Arc;
@@ -8,15 +15,94 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub(crate) struct AppState {
     pub agents: Arc<Agents>,
-    pub pihole_client: Arc<PiholeClient>,
+    /// Held from the hostname check until the agent is in `agents`, so two agents can't claim one name.
+    pub registrations: Arc<tokio::sync::Mutex<()>>,
+    pub dns: Arc<dyn DnsBackend>,
+    pub naming: Arc<NamingPolicy>,
+    pub liveness: Arc<LivenessPolicy>,
+    pub store: Arc<dyn AgentStore>,
+    pub agent_secret: Arc<String>,
+    pub replay_guard: Arc<ReplayGuard>,
+    pub admin_token: Option<Arc<String>>,
+    pub metrics: Arc<ServerMetrics>,
+    pub notifier: Arc<Notifier>,
+    pub events: Arc<EventLog>,
 }
 
+/// Agents keyed by [`agent_key`].
 pub(crate) type Agents = DashMap<String, AgentState>;
 
+/// Cloned out of the map to persist it, no map guard may be held across storage or event I/O.
+#[derive(Clone)]
 pub(crate) struct AgentState {
+    pub id: String,
     pub hostname: String,
     pub agent_version: String,
-    pub ipv4: String,
+    pub ipv4: Option<String>,
+    pub ipv6: Vec<String>,
+    /// Watched interfaces in the agent's priority order, `ipv4` and `ipv6` are derived from them.
+    pub interfaces: Vec<NetworkInterface>,
+    /// CNAMEs published for the agent, as qualified DNS names.
+    pub aliases: Vec<String>,
     pub registered_at: SystemTime,
-    pub last_seen: Instant,
-}
\ No newline at end of file
+    pub last_seen_at: SystemTime,
+    /// When the addresses published in DNS last changed, the first registration for a new agent.
+    pub last_ip_change_at: SystemTime,
+    /// Interval the agent was told to heartbeat at, unknown for agents registered before it was negotiated.
+    pub heartbeat_interval: Option<Duration>,
+    /// When the agent's DNS records were withdrawn after it stayed offline too long.
+    pub retired_at: Option<SystemTime>,
+    /// When the agent announced it was stopping, cleared when it heartbeats or registers again.
+    pub stopped_at: Option<SystemTime>,
+    /// Whether going offline was already notified, so each transition is only reported once.
+    pub reported_offline: bool,
+}
+
+/// Agents are identified by the ID they persist, agents that predate it by their hostname.
+pub(crate) fn agent_key(agent_id: Option<&str>, hostname: &str) -> String {
+    agent_id.filter(|id| !id.is_empty()).unwrap_or(hostname).to_string()
+}
+
+impl AgentState {
+    /// Whether this record was created by an agent that doesn't report a persistent ID.
+    pub(crate) fn is_legacy(&self) -> bool {
+        self.id == self.hostname
+    }
+
+    /// Time since the last registration or heartbeat, zero if the clock went backwards since.
+    pub(crate) fn last_seen_elapsed(&self) -> Duration {
+        SystemTime::now().duration_since(self.last_seen_at).unwrap_or_default()
+    }
+
+    pub(crate) fn set_interfaces(&mut self, interfaces: Vec<NetworkInterface>) {
+        (self.ipv4, self.ipv6) = published_addresses(&interfaces);
+        self.interfaces = interfaces;
+    }
+}
+
+/// Addresses published in DNS for a set of interfaces: a host has a single A record, taken from
+/// the first up interface that holds an IPv4 address, and one AAAA record per IPv6 address on
+/// an up interface.
+pub(crate) fn published_addresses(interfaces: &[NetworkInterface]) -> (Option<String>, Vec<String>) {
+    let up = || interfaces.iter().filter(|interface| interface.up);
+    let ipv4 = up().find_map(|interface| interface.ipv4.clone());
+
+    let mut ipv6: Vec<String> = Vec::new();
+    for ip in up().flat_map(|interface| &interface.ipv6) {
+        if !ipv6.contains(ip) {
+            ipv6.push(ip.clone());
+        }
+    }
+
+    (ipv4, ipv6)
+}
+
+/// Agents that predate interface reporting are tracked as a single unnamed interface.
+pub(crate) fn unnamed_interface(ipv4: Option<String>, ipv6: Vec<String>) -> NetworkInterface {
+    NetworkInterface {
+        name: String::new(),
+        up: true,
+        ipv4,
+        ipv6,
+    }
+}
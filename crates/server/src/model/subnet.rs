@@ -0,0 +1,86 @@
+use std::{net::IpAddr, str::FromStr};
+
+/// An IPv4 or IPv6 network in CIDR notation, e.g. `10.0.2.0/24`. A bare address is a single-host network.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Subnet {
+    network: IpAddr,
+    prefix: u32,
+}
+
+impl FromStr for Subnet {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (address, prefix) = match value.split_once('/') {
+            Some((address, prefix)) => (address, Some(prefix)),
+            None => (value, None),
+        };
+
+        let network: IpAddr = address.parse().map_err(|_| format!("Invalid subnet address {}", address))?;
+        let bits = address_bits(&network);
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u32>()
+                .ok()
+                .filter(|prefix| *prefix <= bits)
+                .ok_or_else(|| format!("Invalid prefix length {} for {}", prefix, address))?,
+            None => bits,
+        };
+
+        Ok(Self { network, prefix })
+    }
+}
+
+impl Subnet {
+    pub(crate) fn contains(&self, ip: &IpAddr) -> bool {
+        let shift = address_bits(&self.network) - self.prefix;
+        let (network, ip) = match (self.network, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => (u32::from(network) as u128, u32::from(*ip) as u128),
+            (IpAddr::V6(network), IpAddr::V6(ip)) => (u128::from(network), u128::from(*ip)),
+            _ => return false,
+        };
+
+        // A /0 shifts every bit out, which `>>` doesn't allow for the full width
+        network.checked_shr(shift).unwrap_or(0) == ip.checked_shr(shift).unwrap_or(0)
+    }
+}
+
+fn address_bits(address: &IpAddr) -> u32 {
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contains(subnet: &str, ip: &str) -> bool {
+        subnet.parse::<Subnet>().unwrap().contains(&ip.parse().unwrap())
+    }
+
+    #[test]
+    fn matches_addresses_inside_the_prefix() {
+        assert!(contains("10.0.2.0/24", "10.0.2.17"));
+        assert!(!contains("10.0.2.0/24", "10.0.3.17"));
+        assert!(contains("fd00::/8", "fd12::1"));
+        assert!(!contains("fd00::/8", "fe80::1"));
+        assert!(contains("0.0.0.0/0", "192.168.1.1"));
+        assert!(contains("10.0.2.5", "10.0.2.5"));
+        assert!(!contains("10.0.2.5", "10.0.2.6"));
+    }
+
+    #[test]
+    fn never_matches_across_families() {
+        assert!(!contains("0.0.0.0/0", "::1"));
+        assert!(!contains("::/0", "127.0.0.1"));
+    }
+
+    #[test]
+    fn rejects_invalid_subnets() {
+        assert!("10.0.2.0/33".parse::<Subnet>().is_err());
+        assert!("10.0.2/24".parse::<Subnet>().is_err());
+        assert!("fd00::/129".parse::<Subnet>().is_err());
+    }
+}
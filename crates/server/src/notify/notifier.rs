@@ -0,0 +1,94 @@
+use crate::config::{WebhookConfig, WebhookFormat};
+use crate::model::event::Event;
+use crate::notify::webhook::deliver;
+use core::logging::{debug, error, warn};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use utoipa::ToSchema;
+
+const MAX_ATTEMPTS: u32 = 5;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Fans events out to the configured webhooks in the background, retrying failed deliveries.
+pub(crate) struct Notifier {
+    client: reqwest::Client,
+    webhooks: Arc<Vec<WebhookConfig>>,
+    sender: mpsc::UnboundedSender<Event>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub(crate) struct WebhookTestResult {
+    /// Position of the webhook in the configuration, URLs may carry tokens so they are not echoed
+    pub webhook: usize,
+    #[schema(value_type = String)]
+    pub format: WebhookFormat,
+    pub delivered: bool,
+    pub error: Option<String>,
+}
+
+impl Notifier {
+    pub(crate) fn start(client: reqwest::Client, webhooks: Vec<WebhookConfig>) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<Event>();
+        let webhooks = Arc::new(webhooks);
+
+        {
+            let client = client.clone();
+            let webhooks = webhooks.clone();
+            tokio::spawn(async move {
+                while let Some(event) = receiver.recv().await {
+                    for webhook in webhooks.iter().filter(|webhook| webhook.wants(&event)) {
+                        tokio::spawn(deliver_with_retry(client.clone(), webhook.clone(), event.clone()));
+                    }
+                }
+            });
+        }
+
+        Self {
+            client,
+            webhooks,
+            sender,
+        }
+    }
+
+    pub(crate) fn notify(&self, event: Event) {
+        debug!("Event {}: {}", event.kind(), event.message());
+        if self.sender.send(event).is_err() {
+            error!("Notifier stopped, dropping event");
+        }
+    }
+
+    /// Sends a test event to every webhook once, without retrying.
+    pub(crate) async fn test(&self) -> Vec<WebhookTestResult> {
+        let mut results = Vec::with_capacity(self.webhooks.len());
+
+        for (index, webhook) in self.webhooks.iter().enumerate() {
+            let result = deliver(&self.client, webhook, &Event::Test).await;
+            results.push(WebhookTestResult {
+                webhook: index,
+                format: webhook.format,
+                delivered: result.is_ok(),
+                error: result.err(),
+            });
+        }
+
+        results
+    }
+}
+
+async fn deliver_with_retry(client: reqwest::Client, webhook: WebhookConfig, event: Event) {
+    let mut backoff = INITIAL_BACKOFF;
+
+    for attempt in 1..=MAX_ATTEMPTS {
+        match deliver(&client, &webhook, &event).await {
+            Ok(_) => return,
+            Err(e) if attempt < MAX_ATTEMPTS => {
+                warn!("Failed to deliver {} to webhook, retrying in {:?}: {}", event.kind(), backoff, e);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+            }
+            Err(e) => error!("Giving up delivering {} to webhook after {} attempts: {}", event.kind(), MAX_ATTEMPTS, e),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use crate::config::{WebhookConfig, WebhookFormat};
+use crate::model::event::Event;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Sends `event` to one webhook, shaped for the service it points at.
+pub(crate) async fn deliver(client: &reqwest::Client, webhook: &WebhookConfig, event: &Event) -> Result<(), String> {
+    let title = format!("PiWatch: {}", event.title());
+    let message = event.message();
+
+    let request = match webhook.format {
+        WebhookFormat::Generic => client.post(&webhook.url).json(&serde_json::json!({
+            "event": event,
+            "title": title,
+            "message": message,
+            "timestamp": SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+        })),
+        // ntfy takes the message as a plain text body on the topic URL
+        WebhookFormat::Ntfy => client
+            .post(&webhook.url)
+            .header("Title", title)
+            .header("Tags", if event.is_alert() { "warning" } else { "information_source" })
+            .header("Priority", if event.is_alert() { "high" } else { "default" })
+            .body(message),
+        // Gotify expects the application token in the URL, e.g. https://gotify/message?token=...
+        WebhookFormat::Gotify => client.post(&webhook.url).json(&serde_json::json!({
+            "title": title,
+            "message": message,
+            "priority": if event.is_alert() { 8 } else { 4 },
+        })),
+        WebhookFormat::Slack => client.post(&webhook.url).json(&serde_json::json!({
+            "text": format!("*{}*\n{}", title, message),
+        })),
+    };
+
+    let response = request.send().await.map_err(|e| e.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("HTTP {}", response.status()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use axum::Json;
+use utoipa::{
+    openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+use crate::handler::{agent, deregister, events, heart_beat, metric, notification, reconcile, remove, stream};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "PiWatch"),
+    servers((url = "/api/v1")),
+    paths(
+        agent::register,
+        agent::update_ip,
+        heart_beat::heartbeat,
+        deregister::deregister,
+        metric::list_agents,
+        metric::get_agent,
+        events::agent_events,
+        events::list_events,
+        stream::stream_events,
+        stream::stream_events_ws,
+        metric::stats,
+        metric::prometheus_metrics,
+        reconcile::run_reconcile,
+        notification::test_notifications,
+        remove::remove_agent,
+    ),
+    modifiers(&SecuritySchemes),
+)]
+pub(crate) struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        // Agents also send the Unix timestamp they signed in the x-piwatch-timestamp header
+        components.add_security_scheme(
+            "agent_signature",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::with_description(
+                core::auth::signature::SIGNATURE_HEADER,
+                "Hex HMAC-SHA256 of `{METHOD} {path}\\n{timestamp}.{body}` keyed with the shared agent secret, `path` relative to /api/v1",
+            ))),
+        );
+        components.add_security_scheme(
+            "admin_token",
+            SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+        );
+    }
+}
+
+pub(crate) async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
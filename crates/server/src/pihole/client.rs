@@ -2,7 +2,9 @@ use tokio::sync::Mutex;
 
 use core::logging::{debug, info, trace};
 use url::{Url, form_urlencoded};
-use crate::pihole::{dto::AuthResponse};
+use crate::dns::backend::{DnsBackend, DnsFuture};
+use crate::model::dns_record::DnsRecord;
+use crate::pihole::{dto::{AuthResponse, ErrorResponse, HostsResponse}};
 
 pub(crate) struct PiholeClient {
     client: reqwest::Client,
@@ -14,7 +16,7 @@ pub(crate) struct PiholeClient {
 impl PiholeClient {
     pub(crate) fn new(client: reqwest::Client, pihole_url: &str, pihole_pass: &str) -> Self {
         Self {
-            client: client,
+            client,
             pihole_url: format!("{}/{}", pihole_url, "api"),
             pihole_pass: pihole_pass.to_string(),
             current_sid: Mutex::new(None),
@@ -22,60 +24,132 @@ impl PiholeClient {
     }
 
     pub(crate) async fn put_ip(&self, hostname: &str, ip: &str) -> Result<(), Box<dyn std::error::Error>> {
-        self.use_auth().await?;
+        match self.put_entry("config/dns/hosts", &format!("{} {}", ip, hostname)).await {
+            Ok(true) => info!("Successfully updated IP for {} to {}", hostname, ip),
+            Ok(false) => debug!("IP {} for {} already present", ip, hostname),
+            Err(e) => return Err(format!("Failed to put IP: {}", e).into()),
+        }
 
-        let kv = form_urlencoded::byte_serialize(format!("{} {}", ip, hostname).as_bytes()).collect::<String>();
-        let api_path = format!("{}/{}", self.api_path("config/dns/hosts"), kv);
+        Ok(())
+    }
 
-        let url = Url::parse(&api_path)?;
-        trace!("Update IP URL: {}", &url);
+    pub(crate) async fn delete_ip(&self, hostname: &str, ip: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match self.delete_entry("config/dns/hosts", &format!("{} {}", ip, hostname)).await {
+            Ok(true) => info!("Successfully deleted IP for {}", hostname),
+            Ok(false) => debug!("IP {} for {} already absent", ip, hostname),
+            Err(e) => return Err(format!("Failed to delete IP: {}", e).into()),
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn put_cname(&self, alias: &str, target: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match self.put_entry("config/dns/cnameRecords", &format!("{},{}", alias, target)).await {
+            Ok(true) => info!("Successfully pointed CNAME {} to {}", alias, target),
+            Ok(false) => debug!("CNAME {} to {} already present", alias, target),
+            Err(e) => return Err(format!("Failed to put CNAME: {}", e).into()),
+        }
+
+        Ok(())
+    }
+
+    pub(crate) async fn delete_cname(&self, alias: &str, target: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match self.delete_entry("config/dns/cnameRecords", &format!("{},{}", alias, target)).await {
+            Ok(true) => info!("Successfully deleted CNAME {}", alias),
+            Ok(false) => debug!("CNAME {} to {} already absent", alias, target),
+            Err(e) => return Err(format!("Failed to delete CNAME: {}", e).into()),
+        }
+
+        Ok(())
+    }
+
+    /// Adds `entry` to a Pi-hole config list, returns `false` if it was already there.
+    async fn put_entry(&self, list: &str, entry: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        self.use_auth().await?;
+
+        let url = self.entry_url(list, entry)?;
+        trace!("Put entry URL: {}", &url);
 
         let sid: String = self.get_current_sid().await.ok_or("Unexpected authentication failure")?;
 
-        // TODO: handle error when hostname-ip already exists
-        match self.client
+        let resp = self.client
             .put(url)
             .header("sid",  sid)
             .send()
-            .await {
-                Ok(resp) => {
-                    if resp.status().is_success() {
-                        info!("Successfully updated IP for {} to {}", hostname, ip);
-                        Ok(())
-                    } else {
-                        Err(format!("Failed to put IP: HTTP {}", resp.status()).into())
-                    }
-                },
-                Err(e) => Err(format!("Failed to put IP: {}", e).into()),
-            }
+            .await?;
+
+        if resp.status().is_success() {
+            return Ok(true);
+        }
+
+        let status = resp.status();
+        // Pi-hole enforces unique entries, an existing one is what we wanted anyway
+        if status == reqwest::StatusCode::BAD_REQUEST
+            && resp.json::<ErrorResponse>().await.is_ok_and(|e| e.error.message == "Item already present") {
+            return Ok(false);
+        }
+
+        Err(format!("HTTP {}", status).into())
     }
 
-    pub(crate) async fn delete_ip(&self, hostname: &str, ip: &str) -> Result<(), Box<dyn std::error::Error>> {
+    /// Removes `entry` from a Pi-hole config list, returns `false` if it wasn't there.
+    async fn delete_entry(&self, list: &str, entry: &str) -> Result<bool, Box<dyn std::error::Error>> {
         self.use_auth().await?;
-        
-        let kv = form_urlencoded::byte_serialize(format!("{} {}", ip, hostname).as_bytes()).collect::<String>();
-        let api_path = format!("{}/{}", self.api_path("config/dns/hosts"), kv);
 
-        let url = Url::parse(&api_path)?;
-        trace!("Delete IP URL: {}", &url);
+        let url = self.entry_url(list, entry)?;
+        trace!("Delete entry URL: {}", &url);
 
         let sid: String = self.get_current_sid().await.ok_or("Unexpected authentication failure")?;
 
-        match self.client
+        let resp = self.client
             .delete(url)
             .header("sid",  sid)
             .send()
-            .await {
-                Ok(resp) => {
-                    if resp.status().is_success() {
-                        info!("Successfully deleted IP for {}", hostname);
-                        Ok(())
-                    } else {
-                        Err(format!("Failed to delete IP: HTTP {}", resp.status()).into())
-                    }
-                },
-                Err(e) => Err(format!("Failed to delete IP: {}", e).into()),
-            }
+            .await?;
+
+        match resp.status() {
+            status if status.is_success() => Ok(true),
+            reqwest::StatusCode::NOT_FOUND => Ok(false),
+            status => Err(format!("HTTP {}", status).into()),
+        }
+    }
+
+    fn entry_url(&self, list: &str, entry: &str) -> Result<Url, url::ParseError> {
+        let entry = form_urlencoded::byte_serialize(entry.as_bytes()).collect::<String>();
+        Url::parse(&format!("{}/{}", self.api_path(list), entry))
+    }
+
+    pub(crate) async fn list_hosts(&self) -> Result<Vec<DnsRecord>, Box<dyn std::error::Error>> {
+        self.use_auth().await?;
+
+        let sid: String = self.get_current_sid().await.ok_or("Unexpected authentication failure")?;
+
+        let response = self.client
+            .get(self.api_path("config/dns/hosts"))
+            .header("sid",  sid)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(format!("Failed to list hosts: HTTP {}", response.status()).into());
+        }
+
+        // Each line is "ip name [aliases...]", flatten it to one record per name
+        let hosts = response.json::<HostsResponse>().await?;
+        let records = hosts.config.dns.hosts
+            .iter()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let ip = parts.next()?;
+                Some(parts.map(move |hostname| DnsRecord {
+                    hostname: hostname.to_string(),
+                    ip: ip.to_string(),
+                }))
+            })
+            .flatten()
+            .collect();
+
+        Ok(records)
     }
 
     async fn use_auth(&self) -> Result<(), Box<dyn std::error::Error>> {
@@ -131,10 +205,7 @@ impl PiholeClient {
 
     async fn get_current_sid(&self) -> Option<String> {
         let sid_lock = self.current_sid.lock().await;
-        match &*sid_lock {
-            Some(s) => Some(s.clone()),
-            None => None,
-        }
+        sid_lock.clone()
     }
 
     async fn set_current_sid(&self, sid: String) {
@@ -142,4 +213,34 @@ impl PiholeClient {
         *sid_lock = Some(sid);
     }
 
-}
\ No newline at end of file
+}
+
+impl DnsBackend for PiholeClient {
+    fn name(&self) -> &'static str {
+        "pihole"
+    }
+
+    fn upsert_host<'a>(&'a self, hostname: &'a str, ip: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(self.put_ip(hostname, ip))
+    }
+
+    fn remove_host<'a>(&'a self, hostname: &'a str, ip: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(self.delete_ip(hostname, ip))
+    }
+
+    fn list_hosts(&self) -> DnsFuture<'_, Vec<DnsRecord>> {
+        Box::pin(PiholeClient::list_hosts(self))
+    }
+
+    fn upsert_cname<'a>(&'a self, alias: &'a str, target: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(self.put_cname(alias, target))
+    }
+
+    fn remove_cname<'a>(&'a self, alias: &'a str, target: &'a str) -> DnsFuture<'a, ()> {
+        Box::pin(self.delete_cname(alias, target))
+    }
+
+    fn health(&self) -> DnsFuture<'_, ()> {
+        Box::pin(self.use_auth())
+    }
+}
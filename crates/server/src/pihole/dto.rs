@@ -9,4 +9,28 @@ pub(crate) struct AuthResponse {
 pub (crate) struct AuthSession {
     pub(crate) valid: bool,
     pub(crate) sid: Option<String>,
-}
\ No newline at end of file
+}
+#[derive(Deserialize)]
+pub(crate) struct HostsResponse {
+    pub(crate) config: HostsConfig,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct HostsConfig {
+    pub(crate) dns: HostsDns,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct HostsDns {
+    pub(crate) hosts: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ErrorResponse {
+    pub(crate) error: ErrorDetail,
+}
+
+#[derive(Deserialize)]
+pub(crate) struct ErrorDetail {
+    pub(crate) message: String,
+}
@@ -0,0 +1,132 @@
+use crate::model::{dns_record::DnsRecord, event::Event, state::AppState};
+use core::logging::{error, info, warn};
+use serde::Serialize;
+use utoipa::ToSchema;
+use std::{collections::HashSet, time::Duration};
+
+#[derive(Serialize, ToSchema, Default)]
+pub(crate) struct ReconcileReport {
+    pub dry_run: bool,
+    pub added: Vec<DnsRecord>,
+    pub removed: Vec<DnsRecord>,
+    pub failed: Vec<String>,
+}
+
+/// Brings the DNS backend's host records back in line with the records PiWatch intends to publish.
+///
+/// Missing records of tracked agents are added, other addresses published for a tracked
+/// hostname are removed, and records of hostnames PiWatch no longer tracks are dropped.
+pub(crate) async fn reconcile(state: &AppState, dry_run: bool) -> Result<ReconcileReport, Box<dyn std::error::Error>> {
+    let actual: HashSet<DnsRecord> = match state.dns.list_hosts().await {
+        Ok(records) => records.into_iter().collect(),
+        Err(e) => {
+            state.events.dns_sync(state.dns.name(), Err(e.to_string()));
+            return Err(e);
+        }
+    };
+    let owned = state.store.load_dns_records()?;
+
+    // The ledger is keyed by agent hostname, the backend holds the DNS names derived from them
+    let published = |record: &DnsRecord| {
+        state.naming.dns_name(&record.hostname).map(|hostname| DnsRecord {
+            hostname,
+            ip: record.ip.clone(),
+        })
+    };
+    let tracked_hostnames: HashSet<String> = state.agents.iter().map(|agent| agent.hostname.clone()).collect();
+    let tracked_names: HashSet<String> = tracked_hostnames
+        .iter()
+        .filter_map(|hostname| state.naming.dns_name(hostname))
+        .collect();
+
+    let (desired, untracked): (Vec<DnsRecord>, Vec<DnsRecord>) = owned
+        .into_iter()
+        .partition(|record| tracked_hostnames.contains(&record.hostname));
+    let desired: HashSet<DnsRecord> = desired.iter().filter_map(published).collect();
+
+    let to_add: Vec<DnsRecord> = desired.difference(&actual).cloned().collect();
+    let to_remove: Vec<DnsRecord> = actual
+        .iter()
+        .filter(|record| tracked_names.contains(&record.hostname) && !desired.contains(record))
+        .cloned()
+        .chain(untracked.iter().filter_map(published).filter(|record| actual.contains(record)))
+        .collect();
+
+    let mut report = ReconcileReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    if dry_run {
+        report.added = to_add;
+        report.removed = to_remove;
+        record(state, &report);
+        return Ok(report);
+    }
+
+    for record in to_add {
+        match state.dns.upsert_host(&record.hostname, &record.ip).await {
+            Ok(_) => report.added.push(record),
+            Err(e) => report.failed.push(format!("add {} {}: {}", record.ip, record.hostname, e)),
+        }
+    }
+
+    for record in to_remove {
+        match state.dns.remove_host(&record.hostname, &record.ip).await {
+            Ok(_) => report.removed.push(record),
+            Err(e) => report.failed.push(format!("remove {} {}: {}", record.ip, record.hostname, e)),
+        }
+    }
+
+    state.events.dns_sync(state.dns.name(), match report.failed.is_empty() {
+        true => Ok(()),
+        false => Err(report.failed.join("; ")),
+    });
+
+    for record in &untracked {
+        if let Err(e) = state.store.remove_dns_record(record) {
+            error!("Failed to forget DNS record {} {}: {}", record.ip, record.hostname, e);
+        }
+    }
+
+    record(state, &report);
+    Ok(report)
+}
+
+fn record(state: &AppState, report: &ReconcileReport) {
+    state.events.record(Event::Reconciled {
+        dry_run: report.dry_run,
+        added: report.added.len(),
+        removed: report.removed.len(),
+        failed: report.failed.len(),
+    });
+}
+
+pub(crate) fn spawn(state: AppState, interval: Duration, dry_run: bool) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(interval).await;
+
+            match reconcile(&state, dry_run).await {
+                Ok(report) => log_report(&report),
+                Err(e) => error!("DNS reconciliation failed: {}", e),
+            }
+        }
+    });
+}
+
+fn log_report(report: &ReconcileReport) {
+    let verb = if report.dry_run { "would" } else { "did" };
+
+    for record in &report.added {
+        info!("Reconcile {} add {} {}", verb, record.ip, record.hostname);
+    }
+
+    for record in &report.removed {
+        info!("Reconcile {} remove {} {}", verb, record.ip, record.hostname);
+    }
+
+    for failure in &report.failed {
+        warn!("Reconcile failed to {}", failure);
+    }
+}
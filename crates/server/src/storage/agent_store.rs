@@ -0,0 +1,28 @@
+use crate::model::{dns_record::DnsRecord, state::AgentState};
+use std::time::SystemTime;
+
+pub(crate) type StoreResult<T> = Result<T, Box<dyn std::error::Error>>;
+
+/// Persistence backend for the agent registry.
+///
+/// The in-memory `Agents` map stays the source of truth for request handling,
+/// every mutation is written through to the store so it can be reloaded on boot.
+pub(crate) trait AgentStore: Send + Sync {
+    fn load_agents(&self) -> StoreResult<Vec<AgentState>>;
+
+    fn save_agent(&self, agent: &AgentState) -> StoreResult<()>;
+
+    fn touch_agent(&self, id: &str, last_seen_at: SystemTime) -> StoreResult<()>;
+
+    fn delete_agent(&self, id: &str) -> StoreResult<()>;
+
+    /// DNS records PiWatch intends to publish, whether or not Pi-hole accepted them yet.
+    fn load_dns_records(&self) -> StoreResult<Vec<DnsRecord>>;
+
+    fn add_dns_record(&self, record: &DnsRecord) -> StoreResult<()>;
+
+    fn remove_dns_record(&self, record: &DnsRecord) -> StoreResult<()>;
+
+    /// Replaces every DNS record of `hostname` with one record per address in `ips`.
+    fn set_dns_records(&self, hostname: &str, ips: &[String]) -> StoreResult<()>;
+}
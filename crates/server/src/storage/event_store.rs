@@ -0,0 +1,24 @@
+use crate::model::event::{Event, RecordedEvent};
+use crate::storage::agent_store::StoreResult;
+use std::time::SystemTime;
+
+/// Selects events from the history, oldest first.
+pub(crate) struct EventFilter {
+    pub agent_id: Option<String>,
+    pub since: Option<SystemTime>,
+    pub kind: Option<String>,
+    /// Only events with a greater sequence number.
+    pub after: Option<i64>,
+    pub limit: usize,
+}
+
+/// Persistence backend for the event history.
+pub(crate) trait EventStore: Send + Sync {
+    /// Stores `event`, returning its sequence number.
+    fn append_event(&self, at: SystemTime, event: &Event) -> StoreResult<i64>;
+
+    fn load_events(&self, filter: &EventFilter) -> StoreResult<Vec<RecordedEvent>>;
+
+    /// Drops events recorded before `before`, returning how many were removed.
+    fn prune_events(&self, before: SystemTime) -> StoreResult<usize>;
+}
@@ -0,0 +1,3 @@
+pub mod agent_store;
+pub mod event_store;
+pub mod sqlite;
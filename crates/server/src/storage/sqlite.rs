@@ -0,0 +1,396 @@
+use crate::model::{dns_record::DnsRecord, event::{Event, RecordedEvent}, state::{unnamed_interface, AgentState}};
+use core::dto::network_interface::NetworkInterface;
+use crate::storage::agent_store::{AgentStore, StoreResult};
+use crate::storage::event_store::{EventFilter, EventStore};
+use rusqlite::{Connection, params};
+use std::{
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+// Each entry upgrades the schema by one version, tracked through `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE agents (
+        hostname TEXT PRIMARY KEY NOT NULL,
+        agent_version TEXT NOT NULL,
+        ipv4 TEXT NOT NULL,
+        registered_at INTEGER NOT NULL,
+        last_seen_at INTEGER NOT NULL
+    )",
+    "ALTER TABLE agents RENAME TO agents_v1;
+    CREATE TABLE agents (
+        hostname TEXT PRIMARY KEY NOT NULL,
+        agent_version TEXT NOT NULL,
+        ipv4 TEXT,
+        ipv6 TEXT NOT NULL DEFAULT '',
+        registered_at INTEGER NOT NULL,
+        last_seen_at INTEGER NOT NULL
+    );
+    INSERT INTO agents (hostname, agent_version, ipv4, registered_at, last_seen_at)
+        SELECT hostname, agent_version, ipv4, registered_at, last_seen_at FROM agents_v1;
+    DROP TABLE agents_v1;",
+    "CREATE TABLE dns_records (
+        hostname TEXT NOT NULL,
+        ip TEXT NOT NULL,
+        PRIMARY KEY (hostname, ip)
+    );
+    INSERT INTO dns_records (hostname, ip)
+        SELECT hostname, ipv4 FROM agents WHERE ipv4 IS NOT NULL;",
+    "ALTER TABLE agents ADD COLUMN interfaces TEXT NOT NULL DEFAULT '[]';",
+    "ALTER TABLE agents ADD COLUMN aliases TEXT NOT NULL DEFAULT '';",
+    "ALTER TABLE agents RENAME TO agents_v5;
+    CREATE TABLE agents (
+        id TEXT PRIMARY KEY NOT NULL,
+        hostname TEXT NOT NULL,
+        agent_version TEXT NOT NULL,
+        ipv4 TEXT,
+        ipv6 TEXT NOT NULL DEFAULT '',
+        registered_at INTEGER NOT NULL,
+        last_seen_at INTEGER NOT NULL,
+        interfaces TEXT NOT NULL DEFAULT '[]',
+        aliases TEXT NOT NULL DEFAULT ''
+    );
+    INSERT INTO agents (id, hostname, agent_version, ipv4, ipv6, registered_at, last_seen_at, interfaces, aliases)
+        SELECT hostname, hostname, agent_version, ipv4, ipv6, registered_at, last_seen_at, interfaces, aliases FROM agents_v5;
+    DROP TABLE agents_v5;",
+    "ALTER TABLE agents ADD COLUMN heartbeat_interval_secs INTEGER;",
+    "ALTER TABLE agents ADD COLUMN retired_at INTEGER;",
+    "ALTER TABLE agents ADD COLUMN stopped_at INTEGER;",
+    "CREATE TABLE events (
+        seq INTEGER PRIMARY KEY AUTOINCREMENT,
+        at INTEGER NOT NULL,
+        agent_id TEXT,
+        type TEXT NOT NULL,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX events_agent_id ON events (agent_id, seq);
+    CREATE INDEX events_at ON events (at);",
+    "ALTER TABLE agents ADD COLUMN last_ip_change_at INTEGER;",
+];
+
+pub(crate) struct SqliteAgentStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteAgentStore {
+    pub(crate) fn open(path: &str) -> StoreResult<Self> {
+        let mut conn = Connection::open(path)?;
+        migrate(&mut conn)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+}
+
+impl AgentStore for SqliteAgentStore {
+    fn load_agents(&self) -> StoreResult<Vec<AgentState>> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        let mut stmt = conn.prepare(
+            "SELECT hostname, agent_version, ipv4, ipv6, registered_at, last_seen_at, interfaces, aliases, id, heartbeat_interval_secs, retired_at, stopped_at, last_ip_change_at FROM agents",
+        )?;
+
+        let agents = stmt
+            .query_map([], |row| {
+                let registered_at = from_unix_secs(row.get(4)?);
+                let ipv4: Option<String> = row.get(2)?;
+                let ipv6 = split_list(&row.get::<_, String>(3)?);
+                let mut interfaces: Vec<NetworkInterface> =
+                    serde_json::from_str(&row.get::<_, String>(6)?).unwrap_or_default();
+                if interfaces.is_empty() {
+                    interfaces.push(unnamed_interface(ipv4.clone(), ipv6.clone()));
+                }
+
+                Ok(AgentState {
+                    id: row.get(8)?,
+                    hostname: row.get(0)?,
+                    agent_version: row.get(1)?,
+                    ipv4,
+                    ipv6,
+                    interfaces,
+                    aliases: split_list(&row.get::<_, String>(7)?),
+                    registered_at,
+                    last_seen_at: from_unix_secs(row.get(5)?),
+                    // Agents stored before address changes were tracked last changed when they registered
+                    last_ip_change_at: row.get::<_, Option<i64>>(12)?.map_or(registered_at, from_unix_secs),
+                    heartbeat_interval: row.get::<_, Option<u64>>(9)?.map(Duration::from_secs),
+                    retired_at: row.get::<_, Option<i64>>(10)?.map(from_unix_secs),
+                    stopped_at: row.get::<_, Option<i64>>(11)?.map(from_unix_secs),
+                    reported_offline: false,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(agents)
+    }
+
+    fn save_agent(&self, agent: &AgentState) -> StoreResult<()> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        conn.execute(
+            "INSERT INTO agents (hostname, agent_version, ipv4, ipv6, registered_at, last_seen_at, interfaces, aliases, id, heartbeat_interval_secs, retired_at, stopped_at, last_ip_change_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+             ON CONFLICT(id) DO UPDATE SET
+                hostname = excluded.hostname,
+                agent_version = excluded.agent_version,
+                ipv4 = excluded.ipv4,
+                ipv6 = excluded.ipv6,
+                registered_at = excluded.registered_at,
+                last_seen_at = excluded.last_seen_at,
+                interfaces = excluded.interfaces,
+                aliases = excluded.aliases,
+                heartbeat_interval_secs = excluded.heartbeat_interval_secs,
+                retired_at = excluded.retired_at,
+                stopped_at = excluded.stopped_at,
+                last_ip_change_at = excluded.last_ip_change_at",
+            params![
+                agent.hostname,
+                agent.agent_version,
+                agent.ipv4,
+                agent.ipv6.join(","),
+                to_unix_secs(agent.registered_at),
+                to_unix_secs(agent.last_seen_at),
+                serde_json::to_string(&agent.interfaces)?,
+                agent.aliases.join(","),
+                agent.id,
+                agent.heartbeat_interval.map(|interval| interval.as_secs()),
+                agent.retired_at.map(to_unix_secs),
+                agent.stopped_at.map(to_unix_secs),
+                to_unix_secs(agent.last_ip_change_at),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn touch_agent(&self, id: &str, last_seen_at: SystemTime) -> StoreResult<()> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        conn.execute(
+            "UPDATE agents SET last_seen_at = ?1 WHERE id = ?2",
+            params![to_unix_secs(last_seen_at), id],
+        )?;
+
+        Ok(())
+    }
+
+    fn delete_agent(&self, id: &str) -> StoreResult<()> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        conn.execute("DELETE FROM agents WHERE id = ?1", params![id])?;
+
+        Ok(())
+    }
+
+    fn load_dns_records(&self) -> StoreResult<Vec<DnsRecord>> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        let mut stmt = conn.prepare("SELECT hostname, ip FROM dns_records")?;
+
+        let records = stmt
+            .query_map([], |row| {
+                Ok(DnsRecord {
+                    hostname: row.get(0)?,
+                    ip: row.get(1)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(records)
+    }
+
+    fn add_dns_record(&self, record: &DnsRecord) -> StoreResult<()> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        conn.execute(
+            "INSERT OR IGNORE INTO dns_records (hostname, ip) VALUES (?1, ?2)",
+            params![record.hostname, record.ip],
+        )?;
+
+        Ok(())
+    }
+
+    fn remove_dns_record(&self, record: &DnsRecord) -> StoreResult<()> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        conn.execute(
+            "DELETE FROM dns_records WHERE hostname = ?1 AND ip = ?2",
+            params![record.hostname, record.ip],
+        )?;
+
+        Ok(())
+    }
+
+    fn set_dns_records(&self, hostname: &str, ips: &[String]) -> StoreResult<()> {
+        let mut conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        let tx = conn.transaction()?;
+        tx.execute("DELETE FROM dns_records WHERE hostname = ?1", params![hostname])?;
+        for ip in ips {
+            tx.execute(
+                "INSERT OR IGNORE INTO dns_records (hostname, ip) VALUES (?1, ?2)",
+                params![hostname, ip],
+            )?;
+        }
+        tx.commit()?;
+
+        Ok(())
+    }
+}
+
+impl EventStore for SqliteAgentStore {
+    fn append_event(&self, at: SystemTime, event: &Event) -> StoreResult<i64> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        conn.execute(
+            "INSERT INTO events (at, agent_id, type, data) VALUES (?1, ?2, ?3, ?4)",
+            params![to_unix_secs(at), event.agent_id(), event.kind(), serde_json::to_string(event)?],
+        )?;
+
+        Ok(conn.last_insert_rowid())
+    }
+
+    fn load_events(&self, filter: &EventFilter) -> StoreResult<Vec<RecordedEvent>> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        let mut stmt = conn.prepare(
+            "SELECT seq, at, data FROM events
+             WHERE (?1 IS NULL OR agent_id = ?1)
+               AND (?2 IS NULL OR at >= ?2)
+               AND (?3 IS NULL OR type = ?3)
+               AND seq > ?4
+             ORDER BY seq
+             LIMIT ?5",
+        )?;
+
+        let rows = stmt
+            .query_map(
+                params![
+                    filter.agent_id,
+                    filter.since.map(to_unix_secs),
+                    filter.kind,
+                    filter.after.unwrap_or(0),
+                    filter.limit as i64,
+                ],
+                |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, String>(2)?)),
+            )?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut events = Vec::with_capacity(rows.len());
+        for (seq, at, data) in rows {
+            events.push(RecordedEvent {
+                seq,
+                at: from_unix_secs(at).into(),
+                event: serde_json::from_str(&data)?,
+            });
+        }
+
+        Ok(events)
+    }
+
+    fn prune_events(&self, before: SystemTime) -> StoreResult<usize> {
+        let conn = self.conn.lock().map_err(|_| "Agent store lock poisoned")?;
+        let pruned = conn.execute("DELETE FROM events WHERE at < ?1", params![to_unix_secs(before)])?;
+
+        Ok(pruned)
+    }
+}
+
+fn migrate(conn: &mut Connection) -> StoreResult<()> {
+    apply_migrations(conn, MIGRATIONS)
+}
+
+fn apply_migrations(conn: &mut Connection, migrations: &[&str]) -> StoreResult<()> {
+    let version: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+    // Each step commits together with its version bump, a failure leaves the previous version intact
+    for (i, migration) in migrations.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", i + 1)?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .filter(|ip| !ip.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn to_unix_secs(time: SystemTime) -> i64 {
+    i64::try_from(time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()).unwrap_or(i64::MAX)
+}
+
+fn from_unix_secs(secs: i64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs.max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user_version(conn: &Connection) -> usize {
+        conn.query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn migrates_a_new_database_to_the_latest_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+        // Running again is a no-op
+        migrate(&mut conn).unwrap();
+        assert_eq!(user_version(&conn), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn keeps_agents_from_the_first_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        apply_migrations(&mut conn, &MIGRATIONS[..1]).unwrap();
+        conn.execute(
+            "INSERT INTO agents (hostname, agent_version, ipv4, registered_at, last_seen_at) VALUES ('pi', '0.1.0', '10.0.2.5', 100, 200)",
+            [],
+        ).unwrap();
+
+        migrate(&mut conn).unwrap();
+        let store = SqliteAgentStore { conn: Mutex::new(conn) };
+        let agents = store.load_agents().unwrap();
+
+        assert_eq!(agents.len(), 1);
+        let agent = &agents[0];
+        assert_eq!(agent.id, "pi");
+        assert!(agent.is_legacy());
+        assert_eq!(agent.ipv4.as_deref(), Some("10.0.2.5"));
+        assert_eq!(agent.last_ip_change_at, agent.registered_at);
+        assert_eq!(store.load_dns_records().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn rolls_back_a_failing_migration() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        let migrations = [
+            "CREATE TABLE a (id INTEGER)",
+            "CREATE TABLE b (id INTEGER); INSERT INTO missing VALUES (1);",
+        ];
+
+        assert!(apply_migrations(&mut conn, &migrations).is_err());
+        assert_eq!(user_version(&conn), 1);
+        let tables: i64 = conn
+            .query_row("SELECT count(*) FROM sqlite_master WHERE type = 'table' AND name = 'b'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(tables, 0);
+    }
+
+    #[test]
+    fn pages_events_by_sequence_number() {
+        let store = SqliteAgentStore::open(":memory:").unwrap();
+        for hostname in ["a", "b", "a", "a"] {
+            let event = Event::AgentOnline { id: hostname.to_string(), hostname: hostname.to_string() };
+            store.append_event(SystemTime::now(), &event).unwrap();
+        }
+        let filter = |after, limit| EventFilter { agent_id: Some("a".to_string()), since: None, kind: None, after, limit };
+
+        let first = store.load_events(&filter(None, 2)).unwrap();
+        assert_eq!(first.iter().map(|event| event.seq).collect::<Vec<_>>(), [1, 3]);
+
+        let rest = store.load_events(&filter(Some(3), 2)).unwrap();
+        assert_eq!(rest.iter().map(|event| event.seq).collect::<Vec<_>>(), [4]);
+    }
+}
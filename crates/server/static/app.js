@@ -0,0 +1,250 @@
+"use strict";
+
+const API = "/api/v1";
+const TOKEN_KEY = "piwatch-admin-token";
+const EVENT_TYPES = [
+  "agent_registered", "agent_offline", "agent_online", "agent_stopped", "heartbeat", "ip_reported", "ip_changed",
+  "agent_retired", "agent_removed", "dns_sync_failed", "dns_sync_recovered", "reconciled", "test",
+];
+const ALERTS = ["agent_offline", "dns_sync_failed"];
+const HISTORY_DAYS = 7;
+const HISTORY_SHOWN = 200;
+
+const $ = (id) => document.getElementById(id);
+
+let agents = [];
+let selected = null;
+let refreshTimer = null;
+let connected = false;
+
+
+function ago(secs) {
+  if (secs < 60) return `${secs}s ago`;
+  if (secs < 3600) return `${Math.floor(secs / 60)}m ago`;
+  if (secs < 86400) return `${Math.floor(secs / 3600)}h ago`;
+  return `${Math.floor(secs / 86400)}d ago`;
+}
+
+function el(tag, props = {}, ...children) {
+  const node = Object.assign(document.createElement(tag), props);
+  node.append(...children);
+  return node;
+}
+
+function toast(message) {
+  const box = $("toast");
+  box.textContent = message;
+  box.hidden = false;
+  clearTimeout(box.timer);
+  box.timer = setTimeout(() => (box.hidden = true), 4000);
+}
+
+async function api(method, path, admin = false) {
+  const headers = {};
+  if (admin) headers.Authorization = `Bearer ${$("token").value}`;
+
+  const response = await fetch(API + path, { method, headers });
+  if (!response.ok) {
+    const body = await response.json().catch(() => ({}));
+    throw new Error(body.message || `${response.status} ${response.statusText}`);
+  }
+  return response.status === 204 ? null : response.json();
+}
+
+function status(agent) {
+  if (agent.retired_at) return "retired";
+  if (agent.stopped_at) return "stopped";
+  return agent.online ? "online" : "offline";
+}
+
+async function loadAgents() {
+  try {
+    agents = await api("GET", "/agents?sort=hostname");
+    // local clock time of the last contact, so heartbeats and ticks don't need the server
+    const now = Date.now();
+    agents.forEach((agent) => (agent.seenAt = now - agent.last_seen_sec * 1000));
+    renderAgents();
+  } catch (e) {
+    toast(`Failed to load agents: ${e.message}`);
+  }
+}
+
+// Coalesces the bursts of events a single registration produces
+function scheduleRefresh() {
+  clearTimeout(refreshTimer);
+  refreshTimer = setTimeout(loadAgents, 300);
+}
+
+function renderAgents() {
+  const online = agents.filter((agent) => agent.online).length;
+  $("stats").textContent = `${agents.length} agent(s), ${online} online`;
+  $("empty").hidden = agents.length > 0;
+
+  const rows = agents.map((agent) => {
+    const addresses = agent.addresses.map((address) =>
+      el("span", {
+        className: address.published ? "address" : "address unpublished",
+        title: address.published ? "Published in DNS" : "Not published in DNS",
+      }, `${address.ip} (${address.interface})`));
+
+    const remove = el("button", { textContent: "Remove" });
+    remove.addEventListener("click", (event) => {
+      event.stopPropagation();
+      removeAgent(agent);
+    });
+
+    const row = el("tr", { className: agent.id === selected?.id ? "selected" : "" },
+      el("td", {}, el("span", { className: `badge ${status(agent)}` }, status(agent))),
+      el("td", {}, agent.hostname, el("div", { className: "muted" }, agent.dns_name || "")),
+      el("td", { title: `Changed ${new Date(agent.last_ip_change_at).toLocaleString()}` }, ...addresses),
+      el("td", {}, agent.agent_version),
+      el("td", { className: "last-seen" }),
+      el("td", {}, remove));
+    row.addEventListener("click", () => selectAgent(agent));
+    return row;
+  });
+
+  $("agents").replaceChildren(...rows);
+  tick();
+}
+
+// Keeps the last-seen column moving between refreshes
+function tick() {
+  const now = Date.now();
+  $("agents").querySelectorAll(".last-seen").forEach((cell, i) => {
+    cell.textContent = ago(Math.max(0, Math.floor((now - agents[i].seenAt) / 1000)));
+    cell.title = new Date(agents[i].last_seen_at).toLocaleString();
+  });
+}
+
+// A heartbeat only moves the agent's last-seen time, coming back online arrives as its own event
+function heartbeat(event) {
+  const agent = agents.find((known) => known.id === event.id);
+  if (!agent) return scheduleRefresh();
+
+  agent.seenAt = Date.now();
+  agent.last_seen_at = event.at;
+  tick();
+}
+
+function describe(event) {
+  const name = event.hostname;
+  switch (event.type) {
+    case "agent_registered": return `${name} registered${event.first ? " for the first time" : ""} with ${event.addresses.join(", ") || "no address"}`;
+    case "agent_offline": return `${name} is offline, no heartbeat for ${event.last_seen_sec}s`;
+    case "agent_online": return `${name} is back online`;
+    case "agent_stopped": return `${name} shut down, DNS records ${event.dns_kept ? "kept" : "removed"}`;
+    case "ip_reported": return `${name} reported ${event.event} ${event.ip} on ${event.interface}`;
+    case "ip_changed": return `${name} moved from ${event.old.join(", ") || "no address"} to ${event.new.join(", ") || "no address"}`;
+    case "agent_retired": return `${name} retired after staying offline`;
+    case "agent_removed": return `${name} removed${event.by_admin ? " by an admin" : ""}`;
+    case "dns_sync_failed": return `${event.backend} sync failed: ${event.error}`;
+    case "dns_sync_recovered": return `${event.backend} sync recovered`;
+    case "reconciled": return `Reconciliation ${event.dry_run ? "would add" : "added"} ${event.added} and ${event.dry_run ? "remove" : "removed"} ${event.removed} record(s)`;
+    case "test": return "Test notification";
+    default: return event.type;
+  }
+}
+
+function historyItem(event) {
+  return el("li", { className: ALERTS.includes(event.type) ? "alert" : "" },
+    el("time", {}, new Date(event.at).toLocaleString()),
+    describe(event));
+}
+
+async function loadHistory() {
+  const since = Math.floor(Date.now() / 1000) - HISTORY_DAYS * 86400;
+  const path = selected ? `/agents/${encodeURIComponent(selected.id)}/events` : "/events";
+
+  $("history-title").textContent = selected ? `Events of ${selected.hostname}` : "Recent events";
+  $("history-all").hidden = !selected;
+
+  try {
+    const page = await api("GET", `${path}?since=${since}&limit=1000`);
+    const items = page.events.slice(-HISTORY_SHOWN).reverse().map(historyItem);
+    $("history").replaceChildren(...items);
+  } catch (e) {
+    toast(`Failed to load events: ${e.message}`);
+  }
+}
+
+function selectAgent(agent) {
+  selected = agent;
+  renderAgents();
+  loadHistory();
+}
+
+async function removeAgent(agent) {
+  if (!confirm(`Remove ${agent.hostname} and its DNS records?`)) return;
+  try {
+    await api("DELETE", `/agents/${encodeURIComponent(agent.id)}`, true);
+    toast(`${agent.hostname} removed`);
+    if (selected?.id === agent.id) selectAgent(null);
+    loadAgents();
+  } catch (e) {
+    toast(`Failed to remove ${agent.hostname}: ${e.message}`);
+  }
+}
+
+async function resync() {
+  try {
+    const report = await api("POST", "/reconcile", true);
+    toast(`Added ${report.added.length}, removed ${report.removed.length}, ${report.failed.length} failed`);
+  } catch (e) {
+    toast(`Resync failed: ${e.message}`);
+  }
+}
+
+async function testNotifications() {
+  try {
+    const results = await api("POST", "/notifications/test", true);
+    const delivered = results.filter((result) => result.delivered).length;
+    toast(`${delivered} of ${results.length} webhook(s) delivered`);
+  } catch (e) {
+    toast(`Test failed: ${e.message}`);
+  }
+}
+
+function connect() {
+  const source = new EventSource(`${API}/events/stream`);
+  const live = $("live");
+
+  source.onopen = () => {
+    live.textContent = "live";
+    live.className = "badge online";
+    // catch up on whatever happened while disconnected
+    if (connected) {
+      loadAgents();
+      loadHistory();
+    }
+    connected = true;
+  };
+  source.onerror = () => {
+    live.textContent = "reconnecting";
+    live.className = "badge offline";
+  };
+
+  EVENT_TYPES.forEach((type) => source.addEventListener(type, (message) => {
+    const event = JSON.parse(message.data);
+    // not kept in the history either
+    if (event.type === "heartbeat") return heartbeat(event);
+
+    if (event.id) scheduleRefresh();
+    if (!selected || event.id === selected.id) {
+      const history = $("history");
+      history.prepend(historyItem(event));
+      while (history.children.length > HISTORY_SHOWN) history.lastChild.remove();
+    }
+  }));
+}
+
+$("token").value = localStorage.getItem(TOKEN_KEY) || "";
+$("token").addEventListener("change", () => localStorage.setItem(TOKEN_KEY, $("token").value));
+$("resync").addEventListener("click", resync);
+$("test-notifications").addEventListener("click", testNotifications);
+$("history-all").addEventListener("click", () => selectAgent(null));
+
+setInterval(tick, 1000);
+loadAgents();
+loadHistory();
+connect();